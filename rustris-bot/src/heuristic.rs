@@ -0,0 +1,187 @@
+use rustris_core::{board::Board, position::Position};
+
+/// How much each feature of a board adds to or takes away from how good it is to play on
+///
+/// Board features are usually penalties, so their weights are negative, while clears are
+/// rewarded for the piece that makes them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Weights {
+    /// For every empty cell with a filled cell above it
+    pub holes: f64,
+    /// For every row of every column's height
+    pub height: f64,
+    /// For every row of the highest column's height
+    pub max_height: f64,
+    /// For every row of difference between the heights of neighbouring columns
+    pub bumpiness: f64,
+    /// For every row of every well, a column lower than the columns or walls on both sides
+    pub wells: f64,
+    /// For every slot a T piece could twist into to clear lines
+    pub twist_setups: f64,
+    /// For clearing 1 to 4 lines without a twist
+    pub clears: [f64; 4],
+    /// For clearing 1 to 3 lines with a full twist
+    pub twist_clears: [f64; 3],
+    pub all_clear: f64,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Self {
+            holes: -6f64,
+            height: -0.3,
+            max_height: -0.5,
+            bumpiness: -0.4,
+            wells: -0.3,
+            twist_setups: 1.5,
+            clears: [-1f64, -0.5, 1f64, 6f64],
+            twist_clears: [3f64, 8f64, 12f64],
+            all_clear: 20f64,
+        }
+    }
+}
+
+impl Weights {
+    /// How good the board is to carry on playing on
+    pub fn evaluate(&self, board: &Board) -> f64 {
+        let heights = column_heights(board);
+        let aggregate_height: u32 = heights.iter().sum();
+        let max_height = heights.iter().copied().max().unwrap_or_default();
+        let bumpiness: u32 = heights
+            .windows(2)
+            .map(|pair| pair[0].abs_diff(pair[1]))
+            .sum();
+
+        self.holes * holes(board, &heights) as f64
+            + self.height * aggregate_height as f64
+            + self.max_height * max_height as f64
+            + self.bumpiness * bumpiness as f64
+            + self.wells * wells(&heights) as f64
+            + self.twist_setups * twist_setups(board, max_height) as f64
+    }
+
+    /// The reward for a piece that cleared lines, with or without a full twist
+    pub fn clear(&self, lines: usize, twist: bool, all_clear: bool) -> f64 {
+        let reward = match (lines, twist) {
+            (0, _) => 0f64,
+            (1..=3, true) => self.twist_clears[lines - 1],
+            (_, _) => self.clears[lines.min(4) - 1],
+        };
+        if all_clear {
+            reward + self.all_clear
+        } else {
+            reward
+        }
+    }
+}
+
+/// The height of each column's highest filled cell above the floor
+fn column_heights(board: &Board) -> [u32; 10] {
+    let mut heights = [0; 10];
+    for (x, height) in heights.iter_mut().enumerate() {
+        *height = board
+            .rows
+            .iter()
+            .rposition(|row| row[x])
+            .map_or(0, |y| y as u32 + 1);
+    }
+    heights
+}
+
+fn holes(board: &Board, heights: &[u32; 10]) -> u32 {
+    heights
+        .iter()
+        .enumerate()
+        .map(|(x, height)| {
+            board.rows[..*height as usize]
+                .iter()
+                .filter(|row| !row[x])
+                .count() as u32
+        })
+        .sum()
+}
+
+fn wells(heights: &[u32; 10]) -> u32 {
+    (0..heights.len())
+        .map(|x| {
+            let left = if x == 0 { u32::MAX } else { heights[x - 1] };
+            let right = heights.get(x + 1).copied().unwrap_or(u32::MAX);
+            left.min(right).saturating_sub(heights[x])
+        })
+        .sum()
+}
+
+/// Counts the empty slots shaped like an upside down T, with at least three of the corners
+/// around its centre filled, whose stem finishes a row
+fn twist_setups(board: &Board, max_height: u32) -> u32 {
+    let filled = |x: i32, y: i32| board.is_filled(Position::new(x, y));
+    let mut setups = 0;
+    for y in 1..=max_height as i32 {
+        for x in 1..9 {
+            let slot_empty = !filled(x - 1, y) && !filled(x, y) && !filled(x + 1, y);
+            let stem_empty = !filled(x, y - 1);
+            if !slot_empty || !stem_empty || !filled(x, y - 2) {
+                continue;
+            }
+            let corners = [(-1, 1), (1, 1), (-1, -1), (1, -1)]
+                .iter()
+                .filter(|(dx, dy)| filled(x + dx, y + dy))
+                .count();
+            let stem_finishes_row = (0..10).all(|column| column == x || filled(column, y - 1));
+            if corners >= 3 && stem_finishes_row {
+                setups += 1;
+            }
+        }
+    }
+    setups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measures_heights_holes_and_wells() {
+        let board = Board::from_diagram(
+            "
+            ..#.......
+            .###....#.
+            ##.#....##
+            ",
+        );
+        let heights = column_heights(&board);
+        assert_eq!(heights, [1, 2, 3, 2, 0, 0, 0, 0, 2, 1]);
+        assert_eq!(holes(&board, &heights), 1);
+        // The walls make wells of the columns beside them
+        assert_eq!(wells(&heights), 2);
+    }
+
+    #[test]
+    fn finds_twist_setups_under_overhangs() {
+        let board = Board::from_diagram(
+            "
+            ###.......
+            ##...#####
+            ###.######
+            ",
+        );
+        assert_eq!(twist_setups(&board, 3), 1);
+
+        let board = Board::from_diagram(
+            "
+            ##........
+            ##...#####
+            ###.######
+            ",
+        );
+        assert_eq!(twist_setups(&board, 3), 0);
+    }
+
+    #[test]
+    fn rewards_twist_clears_over_plain_clears() {
+        let weights = Weights::default();
+        assert_eq!(weights.clear(0, true, false), 0f64);
+        assert!(weights.clear(2, true, false) > weights.clear(2, false, false));
+        assert_eq!(weights.clear(4, false, true), 26f64);
+    }
+}
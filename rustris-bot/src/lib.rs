@@ -0,0 +1,186 @@
+use rustris_core::{
+    board::Board,
+    finesse::{finesse_path, FinesseActions, FinesseInput},
+    input::{Action, GameView, Handling, InputActions},
+    piece::{Piece, PieceSet},
+    rules::RuleSet,
+};
+
+mod heuristic;
+mod planner;
+
+pub use heuristic::Weights;
+pub use planner::HeuristicPlanner;
+
+/// Where a planner wants the active piece placed
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Move {
+    /// Whether to hold first and place the piece that comes out of hold instead
+    pub hold: bool,
+    /// Where the piece should lock
+    pub piece: Piece,
+}
+
+/// Decides where each piece a bot plays should go
+pub trait Planner<TPieceSet: PieceSet> {
+    /// Chooses a move for the active piece, or None to drop it where it is
+    fn plan(&mut self, view: &GameView, piece_set: &TPieceSet) -> Option<Move>;
+}
+
+/// An implementation of InputActions that plays the game itself, placing each piece where its
+/// planner chooses
+///
+/// The bot presses keys like a player would, using the fewest presses that reach the planned
+/// placement. It waits [press_delay] seconds between presses, and held movement keys auto
+/// shift with its handling, unless the rules make every player use the same handling.
+pub struct BotInputActions<TPieceSet: PieceSet, TPlanner: Planner<TPieceSet>> {
+    piece_set: TPieceSet,
+    planner: TPlanner,
+    handling: Handling,
+    press_delay: f64,
+    rules: RuleSet,
+    board: Board,
+    active_piece: Option<Piece>,
+    /// The number of pieces that had been locked when the current move was planned
+    planned_for: Option<u32>,
+    target: Option<Move>,
+    /// The presses left to reach the target, found once the piece to place is active
+    presses: Option<FinesseActions>,
+    /// Actions to take on the next frame before pressing anything else
+    pending: Vec<Action>,
+    press_timer: f64,
+    /// Set once the piece has been hard dropped, so it's planned again if it didn't lock
+    dropped: bool,
+    /// The bot stops pressing keys while the game is paused, so none are lost
+    paused: bool,
+}
+
+impl<TPieceSet: PieceSet, TPlanner: Planner<TPieceSet>> BotInputActions<TPieceSet, TPlanner> {
+    pub fn new(
+        piece_set: TPieceSet,
+        planner: TPlanner,
+        handling: Handling,
+        press_delay: f64,
+    ) -> Self {
+        Self {
+            piece_set,
+            planner,
+            handling,
+            press_delay,
+            rules: RuleSet::guideline(),
+            board: Board::new(),
+            active_piece: None,
+            planned_for: None,
+            target: None,
+            presses: None,
+            pending: vec![],
+            press_timer: 0f64,
+            dropped: false,
+            paused: false,
+        }
+    }
+
+    pub fn planner(&self) -> &TPlanner {
+        &self.planner
+    }
+
+    pub fn planner_mut(&mut self) -> &mut TPlanner {
+        &mut self.planner
+    }
+
+    /// Presses the next key on the way to the target
+    fn press(&mut self, piece: Piece, actions: &mut Vec<Action>) {
+        let Some(target) = self.target.as_mut() else {
+            return;
+        };
+        // The piece that comes out of hold is given its presses once it's active
+        if target.hold {
+            target.hold = false;
+            actions.push(Action::Hold);
+            return;
+        }
+
+        let target = target.piece;
+        let presses = self.presses.get_or_insert_with(|| {
+            let path = finesse_path(&self.board, &self.piece_set, &self.rules, piece, &target)
+                .unwrap_or_else(|| {
+                    if self.rules.hard_drop_enabled {
+                        vec![FinesseInput::HardDrop]
+                    } else {
+                        vec![FinesseInput::SoftDrop]
+                    }
+                });
+            FinesseActions::new(path, self.handling)
+        });
+        if let Some(action) = presses.press() {
+            if action == Action::HardDrop {
+                self.dropped = true;
+            }
+            actions.push(action);
+        }
+    }
+}
+
+impl<TPieceSet: PieceSet, TPlanner: Planner<TPieceSet>> InputActions
+    for BotInputActions<TPieceSet, TPlanner>
+{
+    fn observe(&mut self, view: &GameView) {
+        if let Some(handling) = view.rules.handling {
+            self.handling = handling;
+        }
+        self.rules = view.rules.clone();
+        self.paused = view.paused;
+        self.board = view.board.clone();
+        self.active_piece = view.active_piece;
+
+        // Each piece is planned as it becomes active, and again if it comes back after being
+        // dropped, e.g. when finesse training sends it back to spawn
+        let Some(active_piece) = view.active_piece else {
+            return;
+        };
+        if self.planned_for != Some(view.pieces_locked) || self.dropped {
+            self.planned_for = Some(view.pieces_locked);
+            self.target = self.planner.plan(view, &self.piece_set).or(Some(Move {
+                hold: false,
+                piece: active_piece,
+            }));
+            self.dropped = false;
+            // A piece left to lock on its own may still have soft drop held
+            if let Some(action) = self
+                .presses
+                .take()
+                .and_then(|mut presses| presses.release())
+            {
+                self.pending.push(action);
+            }
+        }
+    }
+
+    fn actions(&mut self, delta_time: f64) -> Vec<Action> {
+        if self.paused {
+            return vec![];
+        }
+        let mut actions = std::mem::take(&mut self.pending);
+        let Some(piece) = self.active_piece else {
+            return actions;
+        };
+
+        // Held keys are held until they've done their job
+        if let Some(presses) = self.presses.as_mut().filter(|presses| presses.is_holding()) {
+            actions.extend(presses.hold(&self.board, &self.piece_set, piece, delta_time));
+            self.press_timer = self.press_delay;
+            return actions;
+        }
+        self.press_timer -= delta_time;
+        if self.press_timer > 0f64 {
+            return actions;
+        }
+        self.press_timer = self.press_delay;
+        self.press(piece, &mut actions);
+        actions
+    }
+
+    fn handling(&self) -> Option<Handling> {
+        Some(self.handling)
+    }
+}
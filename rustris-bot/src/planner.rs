@@ -0,0 +1,154 @@
+use rustris_core::{
+    board::Board,
+    input::GameView,
+    piece::{Piece, PieceSet, PieceType, Rotation},
+    placement::placements,
+    twist::{TwistDetector, TwistKind},
+};
+
+use crate::{heuristic::Weights, Move, Planner};
+
+/// A planner that places pieces where a beam search over the next queue and hold finds the best
+/// board, weighing each board with [Weights]
+pub struct HeuristicPlanner<TTwistDetector> {
+    twist_detector: TTwistDetector,
+    pub weights: Weights,
+    /// The number of the best sequences of placements kept after each piece
+    pub beam_width: usize,
+    /// The number of pieces from the next queue to look ahead at, as well as the active piece
+    pub lookahead: usize,
+}
+
+impl<TTwistDetector> HeuristicPlanner<TTwistDetector> {
+    pub fn new(twist_detector: TTwistDetector) -> Self {
+        Self {
+            twist_detector,
+            weights: Weights::default(),
+            beam_width: 8,
+            lookahead: 3,
+        }
+    }
+}
+
+/// A board reached by placing some of the known pieces, and the first move on the way to it
+#[derive(Clone)]
+struct Node {
+    board: Board,
+    /// The piece to place next, or None once every known piece has been placed
+    current: Option<PieceType>,
+    hold: Option<PieceType>,
+    /// The index in the next queue of the first piece that hasn't been played
+    next: usize,
+    /// The rewards for the clears made so far
+    reward: f64,
+    score: f64,
+    first: Option<Move>,
+}
+
+impl<TPieceSet: PieceSet, TTwistDetector: TwistDetector<TPieceSet>> Planner<TPieceSet>
+    for HeuristicPlanner<TTwistDetector>
+{
+    fn plan(&mut self, view: &GameView, piece_set: &TPieceSet) -> Option<Move> {
+        let active_piece = view.active_piece?;
+        let queue: Vec<PieceType> = view
+            .next_piece_types
+            .iter()
+            .copied()
+            .take(self.lookahead)
+            .collect();
+        let spawn = |piece_type| Piece {
+            piece_type,
+            rotation: Rotation::Up,
+            position: view.rules.spawn_position,
+        };
+
+        let mut beam = vec![Node {
+            board: view.board.clone(),
+            current: Some(active_piece.piece_type),
+            hold: view.hold_piece_type,
+            next: 0,
+            reward: 0f64,
+            score: 0f64,
+            first: None,
+        }];
+        let mut best = None;
+        for depth in 0..=queue.len() {
+            let mut children = vec![];
+            for node in &beam {
+                // Taking a piece out of an empty hold plays the queue out a piece early, so the
+                // board is carried on to be weighed against the boards that place the rest
+                let Some(current) = node.current else {
+                    children.push(node.clone());
+                    continue;
+                };
+                let can_hold = if depth == 0 {
+                    view.can_hold
+                } else {
+                    view.rules.hold_enabled
+                };
+
+                // Each option is the piece to place, what's left in hold and the next piece
+                let mut options = vec![(current, node.hold, node.next, false)];
+                if can_hold && node.hold != Some(current) {
+                    match node.hold {
+                        Some(hold) => options.push((hold, Some(current), node.next, true)),
+                        None => {
+                            if let Some(next) = queue.get(node.next) {
+                                options.push((*next, Some(current), node.next + 1, true));
+                            }
+                        }
+                    }
+                }
+
+                for (piece_type, hold, next, used_hold) in options {
+                    // The active piece may already have moved, but anything else spawns
+                    let piece = if depth == 0 && !used_hold {
+                        active_piece
+                    } else {
+                        spawn(piece_type)
+                    };
+                    for placement in placements(&node.board, piece_set, &self.twist_detector, piece)
+                    {
+                        let mut board = node.board.clone();
+                        let lines = board
+                            .lock_piece(
+                                piece_set.units(&piece_type, &placement.piece.rotation),
+                                placement.piece.position,
+                            )
+                            .len();
+                        let reward = node.reward
+                            + self.weights.clear(
+                                lines,
+                                placement.twist == TwistKind::Full,
+                                board.is_all_clear(),
+                            );
+                        let score = reward + self.weights.evaluate(&board);
+                        children.push(Node {
+                            board,
+                            current: queue.get(next).copied(),
+                            hold,
+                            next: next + 1,
+                            reward,
+                            score,
+                            first: node.first.or(Some(Move {
+                                hold: used_hold,
+                                piece: placement.piece,
+                            })),
+                        });
+                    }
+                }
+            }
+            if children.is_empty() {
+                break;
+            }
+
+            children.sort_by(|a, b| b.score.total_cmp(&a.score));
+            children.truncate(self.beam_width);
+            // Boards further ahead have had more pieces placed, so they're only compared with
+            // each other and the boards carried on from playing out the queue
+            best = children[0].first;
+            beam = children;
+        }
+        best
+    }
+}
@@ -0,0 +1,176 @@
+use crate::twist::TwistKind;
+
+/// Everything about a clear that affects how much attack it's worth
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ClearInfo {
+    pub lines: u32,
+    pub twist: TwistKind,
+    /// The number of consecutive clears before this one, so the first clear of a combo is 0
+    pub combo: u32,
+    /// True if this clear and the previous difficult clear were both difficult clears
+    pub back_to_back: bool,
+    pub all_clear: bool,
+}
+
+impl ClearInfo {
+    /// Returns true for clears that continue a back-to-back chain (quads and twist clears)
+    pub fn is_difficult(&self) -> bool {
+        self.lines >= 4 || (self.lines > 0 && self.twist != TwistKind::None)
+    }
+}
+
+/// Decides how many garbage lines a clear sends
+pub trait AttackTable {
+    fn attack(&self, clear: &ClearInfo) -> u32;
+}
+
+/// The lines sent for a clear before any bonuses, shared by the tables below
+fn base_attack(clear: &ClearInfo) -> u32 {
+    match (clear.twist, clear.lines) {
+        (_, 0) => 0,
+        (TwistKind::Mini, lines) => lines - 1,
+        (TwistKind::Full, lines) => 2 * lines,
+        (TwistKind::None, 4) => 4,
+        (TwistKind::None, lines) => lines - 1,
+    }
+}
+
+/// The attack table from the guideline games
+///
+/// Combos add a fixed number of lines from a table, back-to-back clears add 1 and all clears
+/// add 10.
+pub struct GuidelineAttackTable;
+
+impl GuidelineAttackTable {
+    const COMBO_BONUS: [u32; 11] = [0, 1, 1, 2, 2, 3, 3, 4, 4, 4, 5];
+}
+
+impl AttackTable for GuidelineAttackTable {
+    fn attack(&self, clear: &ClearInfo) -> u32 {
+        if clear.lines == 0 {
+            return 0;
+        }
+
+        let mut attack = base_attack(clear);
+        attack += Self::COMBO_BONUS[(clear.combo as usize).min(Self::COMBO_BONUS.len() - 1)];
+        if clear.back_to_back {
+            attack += 1;
+        }
+        if clear.all_clear {
+            attack += 10;
+        }
+        attack
+    }
+}
+
+/// The attack table used by modern online games
+///
+/// Combos multiply the attack by 25% per clear instead of adding a fixed bonus, and long combos
+/// of clears that are worth nothing on their own still send a few lines.
+pub struct ModernAttackTable;
+
+impl AttackTable for ModernAttackTable {
+    fn attack(&self, clear: &ClearInfo) -> u32 {
+        if clear.lines == 0 {
+            return 0;
+        }
+
+        let mut attack = base_attack(clear) as f64;
+        if clear.back_to_back {
+            attack += 1f64;
+        }
+        attack = if attack > 0f64 {
+            attack * (1f64 + 0.25 * clear.combo as f64)
+        } else if clear.combo >= 2 {
+            (1f64 + 1.25 * clear.combo as f64).ln()
+        } else {
+            0f64
+        };
+
+        let mut attack = attack.floor() as u32;
+        if clear.all_clear {
+            attack += 10;
+        }
+        attack
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clear(lines: u32, twist: TwistKind) -> ClearInfo {
+        ClearInfo {
+            lines,
+            twist,
+            combo: 0,
+            back_to_back: false,
+            all_clear: false,
+        }
+    }
+
+    #[test]
+    fn guideline_base_attack() {
+        let attack = |lines, twist| GuidelineAttackTable.attack(&clear(lines, twist));
+        assert_eq!(attack(0, TwistKind::Full), 0);
+        assert_eq!(attack(1, TwistKind::None), 0);
+        assert_eq!(attack(2, TwistKind::None), 1);
+        assert_eq!(attack(3, TwistKind::None), 2);
+        assert_eq!(attack(4, TwistKind::None), 4);
+        assert_eq!(attack(1, TwistKind::Mini), 0);
+        assert_eq!(attack(2, TwistKind::Mini), 1);
+        assert_eq!(attack(1, TwistKind::Full), 2);
+        assert_eq!(attack(2, TwistKind::Full), 4);
+        assert_eq!(attack(3, TwistKind::Full), 6);
+    }
+
+    #[test]
+    fn guideline_bonuses() {
+        let quad = ClearInfo {
+            back_to_back: true,
+            ..clear(4, TwistKind::None)
+        };
+        assert_eq!(GuidelineAttackTable.attack(&quad), 5);
+
+        let single = |combo| ClearInfo {
+            combo,
+            ..clear(1, TwistKind::None)
+        };
+        assert_eq!(GuidelineAttackTable.attack(&single(1)), 1);
+        assert_eq!(GuidelineAttackTable.attack(&single(5)), 3);
+        assert_eq!(GuidelineAttackTable.attack(&single(30)), 5);
+
+        let all_clear = ClearInfo {
+            all_clear: true,
+            ..clear(4, TwistKind::None)
+        };
+        assert_eq!(GuidelineAttackTable.attack(&all_clear), 14);
+    }
+
+    #[test]
+    fn modern_combos_multiply_attack() {
+        let double = |combo| ClearInfo {
+            combo,
+            ..clear(2, TwistKind::Full)
+        };
+        assert_eq!(ModernAttackTable.attack(&double(0)), 4);
+        assert_eq!(ModernAttackTable.attack(&double(2)), 6);
+        assert_eq!(ModernAttackTable.attack(&double(4)), 8);
+
+        let single = |combo| ClearInfo {
+            combo,
+            ..clear(1, TwistKind::None)
+        };
+        assert_eq!(ModernAttackTable.attack(&single(1)), 0);
+        assert_eq!(ModernAttackTable.attack(&single(2)), 1);
+        assert_eq!(ModernAttackTable.attack(&single(10)), 2);
+    }
+
+    #[test]
+    fn difficult_clears() {
+        assert!(clear(4, TwistKind::None).is_difficult());
+        assert!(clear(1, TwistKind::Mini).is_difficult());
+        assert!(!clear(3, TwistKind::None).is_difficult());
+        assert!(!clear(0, TwistKind::Full).is_difficult());
+    }
+}
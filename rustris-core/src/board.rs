@@ -1,14 +1,20 @@
+use serde::{Deserialize, Serialize};
+
 use crate::piece::{Piece, PieceSet};
 
 use super::position::Position;
 
 /// A Board is a collection of rows, each 10 columns wide
 /// The standard board height is 40 rows (20 of which aren't visible above the playfield)
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Board {
     pub rows: Vec<[bool; 10]>,
 }
 
 impl Board {
+    /// The number of rows from the bottom that are visible in the playfield
+    pub const VISIBLE_HEIGHT: usize = 20;
+
     /// Creates an empty board
     pub fn new() -> Self {
         let mut rows = Vec::new();
@@ -22,8 +28,27 @@ impl Board {
         Board { rows }
     }
 
+    /// Creates a board from a diagram with the top row first, using `#` for filled cells and
+    /// `.` for empty ones, e.g. to set up boards in tests. Rows above the diagram are empty.
+    pub fn from_diagram(diagram: &str) -> Self {
+        let mut board = Board::new();
+        let lines: Vec<&str> = diagram
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .collect();
+        for (y, line) in lines.iter().rev().enumerate() {
+            for (x, cell) in line.chars().enumerate() {
+                board.rows[y][x] = cell == '#';
+            }
+        }
+        board
+    }
+
     /// Adds the piece's units permanently to the board
-    pub fn lock_piece(&mut self, units: [Position; 4], offset: Position) -> usize {
+    /// Returns the rows that were cleared, from the bottom up, as they were numbered before
+    /// the rows above them moved down
+    pub fn lock_piece(&mut self, units: [Position; 4], offset: Position) -> Vec<usize> {
         for unit in units {
             let position = unit + offset;
             self.rows[position.y as usize][position.x as usize] = true;
@@ -35,8 +60,8 @@ impl Board {
 
     /// Removes all filled rows
     /// When a row is removed, all rows above it are moved down
-    fn clear_lines(&mut self) -> usize {
-        let mut lines_cleared: usize = 0;
+    fn clear_lines(&mut self) -> Vec<usize> {
+        let mut cleared_rows = Vec::new();
         for row in (0..40).rev() {
             if self.rows[row].iter().all(|it| *it) {
                 for i in row..39 {
@@ -46,10 +71,26 @@ impl Board {
                 self.rows[39] = [
                     false, false, false, false, false, false, false, false, false, false,
                 ];
-                lines_cleared += 1;
+                cleared_rows.insert(0, row);
             }
         }
-        lines_cleared
+        cleared_rows
+    }
+
+    /// Pushes every row up by one and fills the bottom row, except for the hole column
+    /// Anything in the top row is pushed off the board, and holes past the right wall are left
+    /// in the last column
+    ///
+    /// Returns true if the row pushed off the board had any filled cells
+    pub fn insert_garbage(&mut self, hole: usize) -> bool {
+        let pushed_off = self
+            .rows
+            .pop()
+            .is_some_and(|row| row.iter().any(|cell| *cell));
+        let mut row = [true; 10];
+        row[hole.min(row.len() - 1)] = false;
+        self.rows.insert(0, row);
+        pushed_off
     }
 
     pub fn is_all_clear(&self) -> bool {
@@ -74,15 +115,21 @@ impl Board {
     /// Returns true if any unit of the piece is occupying a filled space on the board
     /// or is outside the bounds of the board
     pub fn is_obstructed(&self, units: [Position; 4], position: Position) -> bool {
-        return units
+        units
             .iter()
-            .any(|unit_offset| self.is_filled(position + *unit_offset));
+            .any(|unit_offset| self.is_filled(position + *unit_offset))
     }
 
     pub fn is_filled(&self, position: Position) -> bool {
         if !(0..10).contains(&position.x) || !(0..40).contains(&position.y) {
             return true;
         }
-        return self.rows[position.y as usize][position.x as usize];
+        self.rows[position.y as usize][position.x as usize]
+    }
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Self::new()
     }
 }
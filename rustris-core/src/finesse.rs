@@ -0,0 +1,238 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::{
+    board::Board,
+    input::{Action, Handling},
+    piece::{Direction, Piece, PieceSet, Rotation},
+    placement::piece_cells,
+    position::Position,
+    rules::RuleSet,
+};
+
+/// A single key press in a finesse path
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FinesseInput {
+    /// Taps left, moving the piece one column
+    Left,
+    Right,
+    /// Holds left until the piece can't move any further (DAS to the wall)
+    DasLeft,
+    DasRight,
+    RotateLeft,
+    RotateRight,
+    /// Holds soft drop until the piece lands
+    SoftDrop,
+    HardDrop,
+}
+
+impl FinesseInput {
+    const ALL: [FinesseInput; 7] = [
+        FinesseInput::Left,
+        FinesseInput::Right,
+        FinesseInput::DasLeft,
+        FinesseInput::DasRight,
+        FinesseInput::RotateLeft,
+        FinesseInput::RotateRight,
+        FinesseInput::SoftDrop,
+    ];
+
+    /// The action the input starts with when its key is pressed
+    pub fn action(&self) -> Action {
+        match self {
+            FinesseInput::Left | FinesseInput::DasLeft => Action::MoveLeft,
+            FinesseInput::Right | FinesseInput::DasRight => Action::MoveRight,
+            FinesseInput::RotateLeft => Action::RotateLeft,
+            FinesseInput::RotateRight => Action::RotateRight,
+            FinesseInput::SoftDrop => Action::SoftDropStarted,
+            FinesseInput::HardDrop => Action::HardDrop,
+        }
+    }
+
+    /// Where the piece ends up after the input, or None if it doesn't move
+    pub fn apply(&self, board: &Board, piece_set: &dyn PieceSet, piece: &Piece) -> Option<Piece> {
+        let units = piece_set.units(&piece.piece_type, &piece.rotation);
+        let moved = |offset: Position| {
+            Some(piece.position + offset)
+                .filter(|position| !board.is_obstructed(units, *position))
+                .map(|position| Piece { position, ..*piece })
+        };
+        let cast = |direction: Position| {
+            Some(board.piece_cast(piece_set, *piece, direction))
+                .filter(|position| *position != piece.position)
+                .map(|position| Piece { position, ..*piece })
+        };
+        match self {
+            FinesseInput::Left => moved(Position::left()),
+            FinesseInput::Right => moved(Position::right()),
+            FinesseInput::DasLeft => cast(Position::left()),
+            FinesseInput::DasRight => cast(Position::right()),
+            FinesseInput::RotateLeft => piece_set.rotate_piece(board, piece, Direction::CCW),
+            FinesseInput::RotateRight => piece_set.rotate_piece(board, piece, Direction::CW),
+            FinesseInput::SoftDrop | FinesseInput::HardDrop => cast(Position::down()),
+        }
+    }
+}
+
+/// Finds the fewest key presses that take the piece from where it is to the target placement,
+/// or None if the target can't be reached
+///
+/// The path ends with a hard drop, or with a soft drop onto the stack when the rules don't
+/// allow hard drops. The search uses the piece set's own rotations and kicks, so it works for
+/// any piece set instead of relying on finesse tables, and targets that fill the same cells in
+/// a different rotation count as the same placement.
+///
+/// The path only counts presses, so it's the same whatever the handling. The piece doesn't fall
+/// between presses, so paths don't hold up when gravity is strong enough to move the piece
+/// while it's being placed, e.g. at 20G. [FinesseActions] plays a path with a given handling.
+pub fn finesse_path(
+    board: &Board,
+    piece_set: &dyn PieceSet,
+    rules: &RuleSet,
+    piece: Piece,
+    target: &Piece,
+) -> Option<Vec<FinesseInput>> {
+    let units = piece_set.units(&piece.piece_type, &piece.rotation);
+    if board.is_obstructed(units, piece.position) {
+        return None;
+    }
+    let target_cells = piece_cells(piece_set, target);
+    let lands_on_target = |piece: &Piece| {
+        let landed = Piece {
+            position: board.piece_cast(piece_set, *piece, Position::down()),
+            ..*piece
+        };
+        piece_cells(piece_set, &landed) == target_cells
+    };
+
+    // Every input takes one press, so a breadth first search finds the shortest path
+    let mut previous: HashMap<(Position, Rotation), (Piece, FinesseInput)> = HashMap::new();
+    let mut queue = VecDeque::from([piece]);
+    while let Some(current) = queue.pop_front() {
+        if lands_on_target(&current) {
+            let mut path = if rules.hard_drop_enabled {
+                vec![FinesseInput::HardDrop]
+            } else {
+                // A piece that's already on the stack is left to lock
+                FinesseInput::SoftDrop
+                    .apply(board, piece_set, &current)
+                    .map(|_| FinesseInput::SoftDrop)
+                    .into_iter()
+                    .collect()
+            };
+            let mut step = current;
+            while let Some((from, input)) = previous.get(&(step.position, step.rotation)) {
+                path.push(*input);
+                step = *from;
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        for input in FinesseInput::ALL {
+            if let Some(next) = input.apply(board, piece_set, &current) {
+                let key = (next.position, next.rotation);
+                if key != (piece.position, piece.rotation) && !previous.contains_key(&key) {
+                    previous.insert(key, (current, input));
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Plays a finesse path as the actions a player pressing its keys would make
+///
+/// Held movement keys charge for [delayed_auto_shift](Handling::delayed_auto_shift) seconds
+/// and then auto shift every [auto_repeat_rate](Handling::auto_repeat_rate) seconds, and are
+/// released once the piece reaches the wall or the stack. Soft drop is held until the piece
+/// lands.
+#[derive(Clone, Debug)]
+pub struct FinesseActions {
+    presses: VecDeque<FinesseInput>,
+    handling: Handling,
+    held: Option<FinesseInput>,
+    auto_shift_timer: f64,
+}
+
+impl FinesseActions {
+    pub fn new(path: Vec<FinesseInput>, handling: Handling) -> Self {
+        Self {
+            presses: path.into(),
+            handling,
+            held: None,
+            auto_shift_timer: 0f64,
+        }
+    }
+
+    /// Whether a key is still held from an earlier press, so the next can't be pressed yet
+    pub fn is_holding(&self) -> bool {
+        self.held.is_some()
+    }
+
+    /// Whether every key in the path has been pressed and released
+    pub fn is_finished(&self) -> bool {
+        self.held.is_none() && self.presses.is_empty()
+    }
+
+    /// Presses the next key in the path, returning its action
+    pub fn press(&mut self) -> Option<Action> {
+        if self.held.is_some() {
+            return None;
+        }
+        let input = self.presses.pop_front()?;
+        match input {
+            FinesseInput::DasLeft | FinesseInput::DasRight | FinesseInput::SoftDrop => {
+                self.held = Some(input);
+                self.auto_shift_timer = self.handling.delayed_auto_shift;
+            }
+            _ => (),
+        }
+        Some(input.action())
+    }
+
+    /// The actions of the held key over the next `delta_time` seconds, releasing it once it has
+    /// moved the piece as far as it goes
+    pub fn hold(
+        &mut self,
+        board: &Board,
+        piece_set: &dyn PieceSet,
+        piece: Piece,
+        delta_time: f64,
+    ) -> Vec<Action> {
+        let mut actions = vec![];
+        let (direction, action) = match self.held {
+            Some(FinesseInput::DasLeft) => (Position::left(), Action::AutoShiftLeft),
+            Some(FinesseInput::DasRight) => (Position::right(), Action::AutoShiftRight),
+            Some(FinesseInput::SoftDrop) => {
+                if board.piece_cast(piece_set, piece, Position::down()) == piece.position {
+                    actions.extend(self.release());
+                }
+                return actions;
+            }
+            _ => return actions,
+        };
+
+        let columns =
+            (board.piece_cast(piece_set, piece, direction).x - piece.position.x).unsigned_abs();
+        self.auto_shift_timer -= delta_time;
+        let mut shifts = 0;
+        while shifts < columns && self.auto_shift_timer <= 0f64 {
+            actions.push(action);
+            shifts += 1;
+            self.auto_shift_timer += self.handling.auto_repeat_rate;
+        }
+        if shifts == columns {
+            self.held = None;
+        }
+        actions
+    }
+
+    /// Lets go of the held key, returning the action that stops it if it needs one
+    pub fn release(&mut self) -> Option<Action> {
+        match self.held.take() {
+            Some(FinesseInput::SoftDrop) => Some(Action::SoftDropStopped),
+            _ => None,
+        }
+    }
+}
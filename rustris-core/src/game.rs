@@ -1,16 +1,30 @@
-use crate::twist::TwistDetector;
+use std::{collections::VecDeque, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::twist::{TwistDetector, TwistKind};
 
 use super::{
+    attack::{AttackTable, ClearInfo, GuidelineAttackTable},
     board::Board,
-    input::{Action, InputActions},
+    finesse::{finesse_path, FinesseInput},
+    garbage::GarbageQueue,
+    input::{Action, GameView, InputActions, NoInputActions},
+    mode::{EndlessMode, GameMode, Mode, Outcome, Stats, Verdict},
     piece::{Direction, Piece, PieceSet, PieceType, Rotation},
     position::Position,
+    puzzle::Puzzle,
     queue::Queue,
-    random::Random,
-    renderer::{RenderState, Renderer},
+    random::{random_seed, Random},
+    renderer::{HistoryPosition, NullRenderer, RenderState, Renderer},
+    replay::{Replay, ReplayRecorder},
+    rules::{LockDelay, LockOut, RuleSet, TWENTY_G},
+    score::{HARD_DROP_POINTS, SOFT_DROP_POINTS},
+    snapshot::{GameSnapshot, SnapshotError, SNAPSHOT_VERSION},
 };
 
-struct HoldFeature {
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct HoldFeature {
     can_hold: bool,
     hold_piece_type: Option<PieceType>,
 }
@@ -43,36 +57,314 @@ impl HoldFeature {
     }
 }
 
-struct GravityFeature {
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct GravityFeature {
     drop_timer: f64,
-    lines_per_second: i32,
+    gravity: f64,
+    soft_drop_speed: f64,
+    soft_dropping: bool,
 }
 
 impl GravityFeature {
-    fn new(lines_per_second: i32) -> Self {
+    fn new(gravity: f64, soft_drop_speed: f64) -> Self {
         Self {
             drop_timer: 0f64,
-            lines_per_second,
+            gravity,
+            soft_drop_speed,
+            soft_dropping: false,
+        }
+    }
+
+    /// Soft drop never makes the piece fall slower than gravity already does
+    fn lines_per_second(&self) -> f64 {
+        if self.soft_dropping {
+            self.gravity.max(self.soft_drop_speed)
+        } else {
+            self.gravity
         }
     }
 
-    fn set_lines_per_second(&mut self, lines_per_second: i32) {
+    fn start_soft_drop(&mut self) {
+        let previous_lines_per_second = self.lines_per_second();
+        self.soft_dropping = true;
+        self.update_drop_timer(previous_lines_per_second);
+    }
+
+    fn stop_soft_drop(&mut self) {
+        let previous_lines_per_second = self.lines_per_second();
+        self.soft_dropping = false;
+        self.update_drop_timer(previous_lines_per_second);
+    }
+
+    fn set_gravity(&mut self, gravity: f64) {
+        let previous_lines_per_second = self.lines_per_second();
+        self.gravity = gravity;
+        self.update_drop_timer(previous_lines_per_second);
+    }
+
+    fn update_drop_timer(&mut self, previous_lines_per_second: f64) {
         // We need to update our drop timer so the piece finishes dropping at the previous speed,
         // otherwise the piece will drop too much if the lines_per_second is increased
-        self.drop_timer *= self.lines_per_second as f64 / lines_per_second as f64;
-        self.lines_per_second = lines_per_second;
+        self.drop_timer *= previous_lines_per_second / self.lines_per_second();
     }
 
     fn update_drop(&mut self, delta_time: f64) -> i32 {
+        let lines_per_second = self.lines_per_second();
         self.drop_timer += delta_time;
-        let lines_to_drop = (self.drop_timer * self.lines_per_second as f64).floor() as i32;
+        let lines_to_drop = (self.drop_timer * lines_per_second).floor() as i32;
         if lines_to_drop > 0 {
-            self.drop_timer -= lines_to_drop as f64 / self.lines_per_second as f64;
+            self.drop_timer -= lines_to_drop as f64 / lines_per_second;
         }
         lines_to_drop
     }
 }
 
+/// Times how long the active piece has rested on the stack, for timed lock delay
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct LockFeature {
+    timer: f64,
+    resets: u32,
+    lowest_y: i32,
+}
+
+impl LockFeature {
+    fn new() -> Self {
+        Self {
+            timer: 0f64,
+            resets: 0,
+            lowest_y: 0,
+        }
+    }
+
+    fn reset(&mut self, y: i32) {
+        self.timer = 0f64;
+        self.resets = 0;
+        self.lowest_y = y;
+    }
+
+    /// Dropping to a new lowest row starts the lock delay over
+    fn update_lowest_y(&mut self, y: i32) {
+        if y < self.lowest_y {
+            self.reset(y);
+        }
+    }
+
+    /// Moving a piece on the stack restarts the timer while it has resets left
+    fn move_reset(&mut self, max_resets: u32) {
+        if self.resets < max_resets {
+            self.timer = 0f64;
+            self.resets += 1;
+        }
+    }
+
+    /// Returns true once the piece has rested for long enough to lock
+    fn update(&mut self, delta_time: f64, lock_delay: f64) -> bool {
+        self.timer += delta_time;
+        self.timer >= lock_delay
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct AttackFeature {
+    combo: Option<u32>,
+    back_to_back: bool,
+}
+
+impl AttackFeature {
+    fn new() -> Self {
+        Self {
+            combo: None,
+            back_to_back: false,
+        }
+    }
+
+    /// Updates the combo and back-to-back chain for a locked piece, returning the clear
+    fn lock(&mut self, lines: u32, twist: TwistKind, all_clear: bool) -> ClearInfo {
+        if lines == 0 {
+            self.combo = None;
+            return ClearInfo {
+                lines,
+                twist,
+                combo: 0,
+                back_to_back: false,
+                all_clear,
+            };
+        }
+
+        let combo = self.combo.map_or(0, |combo| combo + 1);
+        self.combo = Some(combo);
+
+        let mut clear = ClearInfo {
+            lines,
+            twist,
+            combo,
+            back_to_back: false,
+            all_clear,
+        };
+        // Clears that aren't difficult break the back-to-back chain, but not clearing doesn't
+        clear.back_to_back = self.back_to_back && clear.is_difficult();
+        self.back_to_back = clear.is_difficult();
+        clear
+    }
+}
+
+/// Counts the key presses used to place the active piece, to compare with the fewest it needed
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub(crate) struct FinesseFeature {
+    /// The active piece as it spawned, before any inputs moved it
+    spawned: Option<Piece>,
+    /// Presses made during the entry delay count towards the piece that spawns after it
+    presses: u32,
+}
+
+impl FinesseFeature {
+    fn spawn(&mut self, piece: Piece) {
+        self.spawned = Some(piece);
+    }
+
+    fn press(&mut self, action: Action) {
+        match action {
+            Action::MoveLeft
+            | Action::MoveRight
+            | Action::RotateLeft
+            | Action::RotateRight
+            | Action::SoftDropStarted => self.presses += 1,
+            _ => (),
+        }
+    }
+
+    /// The number of presses made more than the fewest that place the spawned piece where the
+    /// piece is, not counting the hard drop, which starts the count over for the next piece
+    ///
+    /// Nothing is counted at 20G, where pieces fall as they're moved so the fewest presses
+    /// can't be worked out.
+    fn lock(
+        &mut self,
+        board: &Board,
+        piece_set: &dyn PieceSet,
+        rules: &RuleSet,
+        gravity: f64,
+        piece: &Piece,
+    ) -> u32 {
+        let presses = std::mem::take(&mut self.presses);
+        let spawned = self.spawned.take();
+        if gravity >= TWENTY_G {
+            return 0;
+        }
+        // A piece that can't be placed by any path, e.g. one moved while gravity carried it past
+        // an overhang, has nothing to compare against
+        spawned
+            .and_then(|spawned| finesse_path(board, piece_set, rules, spawned, piece))
+            .map_or(0, |path| {
+                let needed = path
+                    .iter()
+                    .filter(|input| **input != FinesseInput::HardDrop)
+                    .count();
+                presses.saturating_sub(needed as u32)
+            })
+    }
+
+    /// Starts the count over without comparing it, e.g. when the piece is held
+    fn reset(&mut self) {
+        self.presses = 0;
+    }
+}
+
+/// The states a game can go back to with undo
+///
+/// Keeping states means copying the randomizer, so only games whose randomizer can be cloned
+/// keep a [PlacementHistory]. Every other game has [NoHistory], which never keeps anything.
+trait UndoHistory<TRandom: Random<PieceType>> {
+    /// Copies the queue to capture the current state with, or None if no history is kept
+    fn copy_queue(&self, queue: &Queue<PieceType, TRandom>) -> Option<Queue<PieceType, TRandom>>;
+
+    /// Adds the state after a placement, dropping the oldest states past the undo limit
+    fn record(&mut self, state: GameSnapshot<TRandom>, undo_limit: usize);
+
+    /// Takes back the last placement, returning the state the piece before it was played from
+    fn undo(&mut self) -> Option<GameSnapshot<TRandom>>;
+
+    /// Returns the state the last undo took back
+    fn redo(&mut self) -> Option<GameSnapshot<TRandom>>;
+
+    fn position(&self) -> Option<HistoryPosition>;
+
+    fn clear(&mut self);
+}
+
+struct NoHistory;
+
+impl<TRandom: Random<PieceType>> UndoHistory<TRandom> for NoHistory {
+    fn copy_queue(&self, _: &Queue<PieceType, TRandom>) -> Option<Queue<PieceType, TRandom>> {
+        None
+    }
+
+    fn record(&mut self, _: GameSnapshot<TRandom>, _: usize) {}
+
+    fn undo(&mut self) -> Option<GameSnapshot<TRandom>> {
+        None
+    }
+
+    fn redo(&mut self) -> Option<GameSnapshot<TRandom>> {
+        None
+    }
+
+    fn position(&self) -> Option<HistoryPosition> {
+        None
+    }
+
+    fn clear(&mut self) {}
+}
+
+struct PlacementHistory<TRandom: Random<PieceType>> {
+    /// The state after each of the last placements, up to the undo limit, ending with the one
+    /// the current piece is being played from
+    states: VecDeque<GameSnapshot<TRandom>>,
+    /// The states taken back by undo, most recent last
+    undone: Vec<GameSnapshot<TRandom>>,
+}
+
+impl<TRandom: Random<PieceType> + Clone> UndoHistory<TRandom> for PlacementHistory<TRandom> {
+    fn copy_queue(&self, queue: &Queue<PieceType, TRandom>) -> Option<Queue<PieceType, TRandom>> {
+        Some(queue.clone())
+    }
+
+    fn record(&mut self, state: GameSnapshot<TRandom>, undo_limit: usize) {
+        self.states.push_back(state);
+        while self.states.len() > undo_limit + 1 {
+            self.states.pop_front();
+        }
+        self.undone.clear();
+    }
+
+    fn undo(&mut self) -> Option<GameSnapshot<TRandom>> {
+        if self.states.len() < 2 {
+            return None;
+        }
+        self.undone.extend(self.states.pop_back());
+        self.states.back().cloned()
+    }
+
+    fn redo(&mut self) -> Option<GameSnapshot<TRandom>> {
+        let next = self.undone.pop()?;
+        self.states.push_back(next.clone());
+        Some(next)
+    }
+
+    fn position(&self) -> Option<HistoryPosition> {
+        let placement = self.states.len().saturating_sub(1);
+        Some(HistoryPosition {
+            placement,
+            placements: placement + self.undone.len(),
+        })
+    }
+
+    fn clear(&mut self) {
+        self.states.clear();
+        self.undone.clear();
+    }
+}
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum Message {
     Single,
@@ -85,6 +377,144 @@ pub enum Message {
     TwistDouble(PieceType),
     TwistTriple(PieceType),
     AllClear,
+    /// The number of garbage lines a clear sent, before cancelling incoming garbage
+    Attack(u32),
+    /// The number of key presses more than the piece needed
+    FinesseFault(u32),
+}
+
+/// The number of seconds simulated by each call to [Rustris::step]
+pub const FRAME_TIME: f64 = 1f64 / 60f64;
+
+/// Something that happened to the game during a frame
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameEvent {
+    /// A piece was locked onto the board where it landed
+    PieceLocked(Piece),
+    /// The piece that was just locked cleared lines
+    LinesCleared(ClearInfo),
+    /// The piece took this many more key presses than it needed, and goes back to spawn to be
+    /// placed again instead of locking if the rules train finesse
+    FinesseFault(u32),
+    GameOver(Outcome),
+}
+
+/// Everything that happened during a frame simulated by [Rustris::step]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StepResult {
+    pub events: Vec<GameEvent>,
+}
+
+impl StepResult {
+    /// How the game ended, if it ended during the frame
+    pub fn game_over(&self) -> Option<Outcome> {
+        self.events.iter().find_map(|event| match event {
+            GameEvent::GameOver(outcome) => Some(*outcome),
+            _ => None,
+        })
+    }
+}
+
+/// A game without a renderer or input source, which is driven by [Rustris::step]
+pub type HeadlessRustris<TPieceSet, TRandom, TTwistDetector> =
+    Rustris<TPieceSet, TRandom, NoInputActions, NullRenderer, TTwistDetector>;
+
+/// The parts of a game that a [GameMode] can see and change from its hooks
+pub struct ModeContext<'a> {
+    stats: &'a mut Stats,
+    board: &'a mut Board,
+    garbage_rows: &'a mut u32,
+    gravity_feature: &'a mut GravityFeature,
+    seed: u64,
+    rows_inserted: u32,
+    /// Whether inserted garbage pushed any blocks off the top of the board
+    pushed_off: bool,
+}
+
+impl ModeContext<'_> {
+    pub fn stats(&self) -> &Stats {
+        self.stats
+    }
+
+    pub fn stats_mut(&mut self) -> &mut Stats {
+        self.stats
+    }
+
+    pub fn board(&self) -> &Board {
+        self.board
+    }
+
+    /// The seed of the game's garbage, which follows the piece randomizer's seed and is
+    /// recorded in replays, so modes make the same random choices whenever the game is played
+    ///
+    /// Garbage placed by a mode should be drawn from [crate::garbage::MODE_GARBAGE_STREAM] so it
+    /// doesn't repeat the holes of the incoming garbage
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The number of rows at the bottom of the board that are garbage
+    pub fn garbage_rows(&self) -> u32 {
+        *self.garbage_rows
+    }
+
+    /// Raises rows of garbage under the stack, one for each hole column from the bottom up
+    ///
+    /// The active piece is pushed up out of the new rows, or the game tops out if it can't be.
+    /// The game also tops out if the rows push any blocks off the top of the board.
+    pub fn insert_garbage(&mut self, holes: &[usize]) {
+        self.pushed_off |= insert_garbage(self.board, self.garbage_rows, holes);
+        self.rows_inserted += holes.len() as u32;
+    }
+
+    /// Changes how many lines per second pieces fall when they aren't soft dropped
+    pub fn set_gravity(&mut self, lines_per_second: f64) {
+        self.gravity_feature.set_gravity(lines_per_second);
+    }
+}
+
+/// Inserts a row of garbage for each hole, returning true if any blocks were pushed off the top
+/// of the board
+fn insert_garbage(board: &mut Board, garbage_rows: &mut u32, holes: &[usize]) -> bool {
+    let mut pushed_off = false;
+    for hole in holes {
+        pushed_off |= board.insert_garbage(*hole);
+        *garbage_rows = (*garbage_rows + 1).min(board.rows.len() as u32);
+    }
+    pushed_off
+}
+
+/// A copy of the simulation state of a game, which can be restored to rewind or fast-forward it
+///
+/// The input actions are part of the state, so a keyframe of a replayed game also captures how
+/// far through the replay it was. The game's recording isn't copied, since it grows with every
+/// frame: restoring a keyframe cuts the recording back to the frame the keyframe was captured on,
+/// so the game's replay is only complete if the keyframe doesn't skip ahead. The undo history
+/// isn't copied either, and starts again from the restored state.
+pub struct Keyframe<TRandom: Random<PieceType>, TInputActions> {
+    board: Board,
+    active_piece: Option<Piece>,
+    ghost_piece_position: Option<Position>,
+    queue: Queue<PieceType, TRandom>,
+    input_actions: TInputActions,
+    hold_feature: HoldFeature,
+    gravity_feature: GravityFeature,
+    lock_feature: LockFeature,
+    spawn_timer: Option<f64>,
+    initial_rotation: Option<Direction>,
+    garbage: GarbageQueue,
+    outgoing_garbage: u32,
+    garbage_rows: u32,
+    attack_feature: AttackFeature,
+    finesse_feature: FinesseFeature,
+    /// The number of frames recorded when the keyframe was captured
+    replay_frames: u64,
+    paused: bool,
+    quit: bool,
+    mode: Box<dyn GameMode>,
+    pieces_dealt: u32,
+    stats: Stats,
+    outcome: Option<Outcome>,
 }
 
 pub struct Rustris<
@@ -94,6 +524,7 @@ pub struct Rustris<
     TRenderer: Renderer<TPieceSet>,
     TTwistDetector: TwistDetector<TPieceSet>,
 > {
+    rules: RuleSet,
     board: Board,
     piece_set: TPieceSet,
     active_piece: Option<Piece>,
@@ -102,10 +533,36 @@ pub struct Rustris<
     input_actions: TInputActions,
     hold_feature: HoldFeature,
     gravity_feature: GravityFeature,
+    lock_feature: LockFeature,
+    /// The time left before the next piece spawns, while waiting out the entry delay
+    spawn_timer: Option<f64>,
+    /// A rotation made during the entry delay, to apply to the next piece as it spawns
+    initial_rotation: Option<Direction>,
     twist_detector: TTwistDetector,
     renderer: TRenderer,
+    garbage: GarbageQueue,
+    outgoing_garbage: u32,
+    /// The number of rows at the bottom of the board that are garbage
+    garbage_rows: u32,
+    attack_table: Box<dyn AttackTable>,
+    attack_feature: AttackFeature,
+    finesse_feature: FinesseFeature,
+    replay_recorder: ReplayRecorder,
     paused: bool,
+    quit: bool,
+    mode: Box<dyn GameMode>,
+    /// The number of pieces the queue can deal, for puzzles and replays
+    piece_limit: Option<u32>,
+    pieces_dealt: u32,
+    stats: Stats,
+    outcome: Option<Outcome>,
+    /// Whether the game has been started by [init](Self::init) or restored from a snapshot
+    started: bool,
+    /// The states undo can go back to, kept once [enable_undo](Self::enable_undo) is called
+    history: Box<dyn UndoHistory<TRandom>>,
     messages: Vec<Message>,
+    /// What has happened since the start of the current frame
+    events: Vec<GameEvent>,
 }
 
 impl<
@@ -117,117 +574,544 @@ impl<
     > Rustris<TPieceSet, TRandom, TInputActions, TRenderer, TTwistDetector>
 {
     pub fn new(
+        rules: RuleSet,
         piece_set: TPieceSet,
-        queue: Queue<PieceType, TRandom>,
+        random: TRandom,
         input_actions: TInputActions,
         twist_detector: TTwistDetector,
         renderer: TRenderer,
     ) -> Self {
+        // Garbage follows the game's seed so seeded games get the same garbage every time
+        let garbage_seed = random.seed().unwrap_or_else(random_seed);
         Rustris {
             board: Board::new(),
             piece_set,
             active_piece: None,
             ghost_piece_position: None,
-            queue,
+            queue: Queue::new(rules.preview_count, random),
             input_actions,
             hold_feature: HoldFeature::new(),
-            gravity_feature: GravityFeature::new(1),
+            gravity_feature: GravityFeature::new(
+                rules.gravity.lines_per_second(1),
+                rules.soft_drop_speed,
+            ),
+            lock_feature: LockFeature::new(),
+            spawn_timer: None,
+            initial_rotation: None,
             twist_detector,
             renderer,
+            garbage: GarbageQueue::with_seed(0f64, garbage_seed),
+            outgoing_garbage: 0,
+            garbage_rows: 0,
+            attack_table: Box::new(GuidelineAttackTable),
+            attack_feature: AttackFeature::new(),
+            finesse_feature: FinesseFeature::default(),
+            replay_recorder: ReplayRecorder::new(),
             paused: false,
+            quit: false,
+            mode: Box::new(EndlessMode),
+            piece_limit: None,
+            pieces_dealt: 0,
+            stats: Stats::default(),
+            outcome: None,
+            started: false,
+            history: Box::new(NoHistory),
             messages: vec![],
+            events: vec![],
+            rules,
+        }
+    }
+
+    pub fn rules(&self) -> &RuleSet {
+        &self.rules
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn active_piece(&self) -> Option<Piece> {
+        self.active_piece
+    }
+
+    pub fn hold_piece_type(&self) -> Option<PieceType> {
+        self.hold_feature.hold_piece_type
+    }
+
+    pub fn input_actions(&self) -> &TInputActions {
+        &self.input_actions
+    }
+
+    /// The number of pieces that have been locked onto the board
+    pub fn pieces_locked(&self) -> u32 {
+        self.stats.pieces_locked
+    }
+
+    /// Sets one of the built-in modes to play, which should be done before the game starts
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode.create();
+        self.replay_recorder.set_mode(Some(mode));
+    }
+
+    /// Sets the mode that decides when the game ends, which should be done before it starts
+    ///
+    /// Replays can't recreate custom modes, so they're played back in endless mode.
+    pub fn set_game_mode<TGameMode: GameMode + 'static>(&mut self, mode: TGameMode) {
+        self.mode = Box::new(mode);
+        self.replay_recorder.set_mode(None);
+    }
+
+    pub fn mode(&self) -> &dyn GameMode {
+        self.mode.as_ref()
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    /// How the game ended, or None while it's still being played
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.outcome
+    }
+
+    /// The seed of the piece randomizer, if it was seeded
+    pub fn seed(&self) -> Option<u64> {
+        self.queue.random().seed()
+    }
+
+    /// Replaces the table used to work out how much garbage each clear sends
+    pub fn set_attack_table<TAttackTable: AttackTable + 'static>(
+        &mut self,
+        attack_table: TAttackTable,
+    ) {
+        self.attack_table = Box::new(attack_table);
+    }
+
+    /// The total number of garbage lines sent by clears, including lines that only cancelled
+    /// incoming garbage
+    pub fn attack_sent(&self) -> u32 {
+        self.stats.attack_sent
+    }
+
+    /// Sets up the puzzle's board and hold piece, which should be done before the game starts
+    ///
+    /// The game should be created with the puzzle's queue, and fails if the goal hasn't been
+    /// reached once every piece has been played.
+    pub fn set_puzzle(&mut self, puzzle: &Puzzle) {
+        self.board = puzzle.board.clone();
+        self.hold_feature.hold_piece_type = puzzle.hold;
+        self.mode = Mode::Puzzle { goal: puzzle.goal }.create();
+        self.piece_limit = Some(puzzle.pieces.len() as u32);
+        self.replay_recorder.set_puzzle(puzzle);
+    }
+
+    /// Sets up a replay's mode, puzzle and garbage to play it back, which should be done before
+    /// the game starts
+    ///
+    /// The game should be created with the replay's randomizer and input actions, and stops
+    /// dealing pieces once the recorded ones run out rather than making up new ones.
+    pub fn set_replay(&mut self, replay: &Replay) {
+        match (&replay.puzzle, replay.mode) {
+            (Some(puzzle), _) => self.set_puzzle(puzzle),
+            (None, Some(mode)) => self.set_mode(mode),
+            (None, None) => (),
         }
+        self.piece_limit = Some(replay.pieces.len() as u32);
+        self.garbage = replay.garbage_queue();
+    }
+
+    /// Replaces the incoming garbage queue, e.g. to change its messiness or seed, which should
+    /// be done before the game starts so its replay starts with the same queue
+    pub fn set_garbage_queue(&mut self, garbage: GarbageQueue) {
+        self.garbage = garbage;
+    }
+
+    /// Queues garbage lines sent by an opponent, which are recorded to be received again when
+    /// the game is played back
+    pub fn receive_garbage(&mut self, lines: u32) {
+        self.replay_recorder.record_garbage(lines);
+        self.garbage.receive(lines);
+    }
+
+    /// The number of garbage lines waiting to be inserted onto the board
+    pub fn pending_garbage(&self) -> u32 {
+        self.garbage.pending()
+    }
+
+    /// Takes the attack that was left over after cancelling incoming garbage, to be sent to an
+    /// opponent
+    pub fn take_outgoing_garbage(&mut self) -> u32 {
+        std::mem::take(&mut self.outgoing_garbage)
+    }
+
+    /// Returns true once the player has asked to quit the game
+    pub fn is_quit(&self) -> bool {
+        self.quit
     }
 
+    /// Creates a replay of the game played so far
+    pub fn replay(&mut self) -> Replay {
+        let next_piece_types = self.next_piece_types();
+        self.replay_recorder.replay(
+            self.rules.clone(),
+            self.input_actions.handling(),
+            self.queue.random().seed(),
+            self.pieces_dealt as usize,
+            &next_piece_types,
+            &self.garbage,
+        )
+    }
+
+    /// Starts the game, or only initializes the renderer if the game was restored from a
+    /// snapshot
     pub fn init(&mut self) {
         self.renderer.init();
+        if self.started {
+            return;
+        }
+        self.started = true;
+
+        self.run_mode_hook(|mode, context| mode.start(context));
+        if self.outcome.is_some() {
+            return;
+        }
         self.spawn_piece(None);
+        self.record_history();
     }
 
     pub fn update(&mut self, delta_time: f64) {
+        let next_piece_types = self.next_piece_types();
+        self.input_actions.observe(&GameView {
+            rules: &self.rules,
+            board: &self.board,
+            active_piece: self.active_piece,
+            hold_piece_type: self.hold_feature.hold_piece_type,
+            can_hold: self.rules.hold_enabled && self.hold_feature.can_hold,
+            next_piece_types: &next_piece_types,
+            pieces_locked: self.stats.pieces_locked,
+            consecutive_clears: self.attack_feature.combo.map_or(0, |combo| combo + 1),
+            back_to_back: self.attack_feature.back_to_back,
+            paused: self.paused,
+        });
         let actions = self.input_actions.actions(delta_time);
+        self.simulate(delta_time, actions);
+    }
+
+    /// Simulates a frame of [FRAME_TIME] seconds with the given actions instead of the game's
+    /// own input actions, returning what happened during it
+    pub fn step(&mut self, actions: &[Action]) -> StepResult {
+        self.step_by(FRAME_TIME, actions)
+    }
+
+    /// Simulates a frame like [step](Self::step) that lasts for the given number of seconds, e.g.
+    /// to verify a replay recorded at a different frame rate
+    pub fn step_by(&mut self, delta_time: f64, actions: &[Action]) -> StepResult {
+        self.simulate(delta_time, actions.to_vec());
+        StepResult {
+            events: std::mem::take(&mut self.events),
+        }
+    }
+
+    fn simulate(&mut self, delta_time: f64, actions: Vec<Action>) {
+        self.events.clear();
+        self.replay_recorder.record_frame(delta_time, &actions);
+
+        if actions.contains(&Action::Quit) {
+            self.quit = true;
+        }
 
         if actions.contains(&Action::Pause) {
             self.paused = !self.paused;
         }
 
-        if self.paused {
+        for action in &actions {
+            if let Action::ReceiveGarbage(lines) = action {
+                self.garbage.receive(*lines);
+            }
+        }
+
+        if self.paused || self.outcome.is_some() {
+            return;
+        }
+
+        self.stats.time += Duration::from_secs_f64(delta_time);
+        self.run_mode_hook(|mode, context| mode.tick(context, delta_time));
+        if self.outcome.is_some() {
             return;
         }
+        self.update_spawn_timer(delta_time);
 
         for action in actions {
+            self.finesse_feature.press(action);
             match action {
-                Action::MoveLeft => self.move_active_piece(Position::left()),
-                Action::MoveRight => self.move_active_piece(Position::right()),
+                Action::MoveLeft | Action::AutoShiftLeft => {
+                    self.move_active_piece(Position::left())
+                }
+                Action::MoveRight | Action::AutoShiftRight => {
+                    self.move_active_piece(Position::right())
+                }
                 Action::RotateLeft => self.rotate_active_piece(Direction::CCW),
                 Action::RotateRight => self.rotate_active_piece(Direction::CW),
                 Action::Hold => self.hold_active_piece(),
-                Action::HardDrop => self.hard_drop_active_piece(),
-                Action::SoftDropStarted => self.gravity_feature.set_lines_per_second(50),
-                Action::SoftDropStopped => self.gravity_feature.set_lines_per_second(1),
-                Action::Pause => {
+                Action::HardDrop if self.rules.hard_drop_enabled => self.hard_drop_active_piece(),
+                Action::HardDrop => (),
+                Action::SoftDropStarted => self.gravity_feature.start_soft_drop(),
+                Action::SoftDropStopped => self.gravity_feature.stop_soft_drop(),
+                Action::Undo => self.undo(),
+                Action::Redo => self.redo(),
+                Action::Pause | Action::Quit | Action::ReceiveGarbage(_) => {
                     // Already handled above
                 }
             };
         }
 
         self.update_gravity(delta_time);
+        self.update_lock_delay(delta_time);
     }
 
     fn spawn_piece(&mut self, piece_type: Option<PieceType>) {
         self.active_piece = Some(Piece {
             piece_type: if let Some(t) = piece_type {
                 t
+            } else if self.pieces_left() == Some(0) {
+                // Puzzles and replays play the held piece once the queue runs out, and fail once
+                // that's gone too
+                match self.hold_feature.hold_piece_type.take() {
+                    Some(t) => t,
+                    None => {
+                        self.end_game(Outcome::Failed);
+                        return;
+                    }
+                }
             } else {
-                self.queue.next()
+                let t = self.queue.next();
+                self.replay_recorder
+                    .record_piece(self.pieces_dealt as usize, t);
+                self.pieces_dealt += 1;
+                t
             },
             rotation: Rotation::Up,
-            position: Position::new(4, 19),
+            position: self.rules.spawn_position,
         });
+
+        // The game is over if there's no room for the new piece
+        if let Some(piece) = self.active_piece {
+            if self.board.is_obstructed(
+                self.piece_set.units(&piece.piece_type, &piece.rotation),
+                piece.position,
+            ) {
+                self.end_game(Outcome::ToppedOut);
+                return;
+            }
+            self.lock_feature.reset(piece.position.y);
+            self.finesse_feature.spawn(piece);
+        }
+        if let Some(direction) = self.initial_rotation.take() {
+            self.rotate_active_piece(direction);
+        }
+        self.apply_instant_gravity();
+        self.update_ghost_piece_position();
+    }
+
+    /// The number of pieces the queue can still deal, if it has a limit
+    fn pieces_left(&self) -> Option<u32> {
+        self.piece_limit
+            .map(|piece_limit| piece_limit.saturating_sub(self.pieces_dealt))
+    }
+
+    /// The upcoming pieces shown in the next queue, leaving out any the queue can't deal
+    fn next_piece_types(&mut self) -> Vec<PieceType> {
+        let mut next_piece_types = self.queue.next_items().to_vec();
+        if let Some(pieces_left) = self.pieces_left() {
+            next_piece_types.truncate(pieces_left as usize);
+        }
+        next_piece_types
+    }
+
+    /// Runs one of the mode's hooks, then ends the game if the mode's verdict says it's over
+    fn run_mode_hook(&mut self, hook: impl FnOnce(&mut dyn GameMode, &mut ModeContext)) {
+        let level = self.stats.level;
+        let mut context = ModeContext {
+            stats: &mut self.stats,
+            board: &mut self.board,
+            garbage_rows: &mut self.garbage_rows,
+            gravity_feature: &mut self.gravity_feature,
+            seed: self.garbage.seed(),
+            rows_inserted: 0,
+            pushed_off: false,
+        };
+        hook(self.mode.as_mut(), &mut context);
+        let rows_inserted = context.rows_inserted;
+        if context.pushed_off {
+            self.end_game(Outcome::ToppedOut);
+            return;
+        }
+
+        if self.stats.level != level {
+            self.gravity_feature
+                .set_gravity(self.rules.gravity.lines_per_second(self.stats.level));
+        }
+
+        if rows_inserted > 0 {
+            self.raise_active_piece(rows_inserted);
+            if self.outcome.is_some() {
+                return;
+            }
+        }
+        match self.mode.verdict(&self.stats) {
+            Verdict::Continue => (),
+            Verdict::Win => self.end_game(Outcome::Completed),
+            Verdict::Lose => self.end_game(Outcome::Failed),
+        }
+    }
+
+    fn end_game(&mut self, outcome: Outcome) {
+        self.outcome = Some(outcome);
+        self.events.push(GameEvent::GameOver(outcome));
+        self.active_piece = None;
         self.update_ghost_piece_position();
     }
 
     fn update_gravity(&mut self, delta_time: f64) {
-        if let Some(_) = self.active_piece {
-            let lines_to_drop = self.gravity_feature.update_drop(delta_time);
-            if lines_to_drop > 0 {
-                self.move_active_piece(lines_to_drop * Position::down());
+        if self.active_piece.is_none() {
+            return;
+        }
+
+        // Drop one line at a time so the piece lands on the stack when gravity is faster than a
+        // line per frame
+        let lines_to_drop = self.gravity_feature.update_drop(delta_time);
+        for _ in 0..lines_to_drop {
+            match self.active_piece {
+                Some(active_piece) if self.is_on_stack(&active_piece) => {
+                    if self.rules.lock_delay == LockDelay::Gravity {
+                        self.lock_active_piece();
+                    }
+                    break;
+                }
+                Some(_) => {
+                    self.move_active_piece(Position::down());
+                    if self.gravity_feature.soft_dropping {
+                        self.stats.score += SOFT_DROP_POINTS;
+                    }
+                }
+                None => break,
+            }
+        }
+    }
+
+    /// Locks the active piece once it has rested on the stack for the lock delay
+    fn update_lock_delay(&mut self, delta_time: f64) {
+        if let (Some(active_piece), Some((seconds, _))) = (
+            self.active_piece,
+            self.rules.lock_delay.timer(self.stats.level),
+        ) {
+            if self.is_on_stack(&active_piece) && self.lock_feature.update(delta_time, seconds) {
+                self.lock_active_piece();
+            }
+        }
+    }
+
+    /// Spawns the next piece once the entry delay after the last lock has passed
+    fn update_spawn_timer(&mut self, delta_time: f64) {
+        if let Some(spawn_timer) = self.spawn_timer {
+            // Allow for rounding, so a delay of a whole number of frames spawns on time
+            let spawn_timer = spawn_timer - delta_time;
+            if spawn_timer < 1e-9 {
+                self.spawn_timer = None;
+                self.spawn_piece(None);
+            } else {
+                self.spawn_timer = Some(spawn_timer);
+            }
+        }
+    }
+
+    /// Returns true if the piece can't fall any further
+    fn is_on_stack(&self, piece: &Piece) -> bool {
+        self.board.is_obstructed(
+            self.piece_set.units(&piece.piece_type, &piece.rotation),
+            piece.position + Position::down(),
+        )
+    }
+
+    /// Updates the lock delay after the active piece was moved or rotated from [previous]
+    fn update_lock_feature(&mut self, previous: &Piece) {
+        if let (Some(active_piece), Some((_, resets))) = (
+            self.active_piece,
+            self.rules.lock_delay.timer(self.stats.level),
+        ) {
+            if self.is_on_stack(previous) {
+                self.lock_feature.move_reset(resets);
             }
+            self.lock_feature.update_lowest_y(active_piece.position.y);
         }
     }
 
     fn move_active_piece(&mut self, offset: Position) {
         if let Some(mut active_piece) = self.active_piece {
-            let mut target_position = active_piece.position.clone();
+            let mut target_position = active_piece.position;
             target_position += offset;
             if !self.board.is_obstructed(
                 self.piece_set
                     .units(&active_piece.piece_type, &active_piece.rotation),
                 target_position,
             ) {
-                active_piece.position = target_position.clone();
+                let previous = active_piece;
+                active_piece.position = target_position;
                 self.active_piece = Some(active_piece);
+                self.update_lock_feature(&previous);
+                self.apply_instant_gravity();
                 self.update_ghost_piece_position();
             }
         }
     }
 
+    /// Drops the active piece straight onto the stack when gravity is 20G or faster, so it
+    /// never hangs in the air after spawning or moving
+    fn apply_instant_gravity(&mut self) {
+        if self.gravity_feature.gravity < TWENTY_G {
+            return;
+        }
+        if let Some(mut active_piece) = self.active_piece {
+            active_piece.position =
+                self.board
+                    .piece_cast(&self.piece_set, active_piece, Position::down());
+            self.active_piece = Some(active_piece);
+            self.lock_feature.update_lowest_y(active_piece.position.y);
+        }
+    }
+
     fn rotate_active_piece(&mut self, direction: Direction) {
+        // Rotating during the entry delay rotates the next piece as it spawns
+        if self.active_piece.is_none() && self.spawn_timer.is_some() && self.rules.initial_rotation
+        {
+            self.initial_rotation = Some(direction);
+            return;
+        }
         if let Some(active_piece) = self.active_piece {
             if let Some(piece) = self
                 .piece_set
                 .rotate_piece(&self.board, &active_piece, direction)
             {
                 self.active_piece = Some(piece);
+                self.update_lock_feature(&active_piece);
+                self.apply_instant_gravity();
                 self.update_ghost_piece_position();
             }
         }
     }
 
     fn hold_active_piece(&mut self) {
+        if !self.rules.hold_enabled {
+            return;
+        }
         // We can only hold if we have an active piece spawned
         if let Some(active_piece) = self.active_piece {
+            // Holding into an empty hold needs a piece from the queue
+            if self.hold_feature.hold_piece_type.is_none() && self.pieces_left() == Some(0) {
+                return;
+            }
             if let Some(piece_to_spawn) = self.hold_feature.hold(active_piece.piece_type) {
+                self.finesse_feature.reset();
                 self.spawn_piece(piece_to_spawn);
             }
         }
@@ -235,9 +1119,12 @@ impl<
 
     fn hard_drop_active_piece(&mut self) {
         if let Some(mut active_piece) = self.active_piece {
-            active_piece.position =
+            let drop_position =
                 self.board
                     .piece_cast(&self.piece_set, active_piece, Position::down());
+            let lines_dropped = active_piece.position.y - drop_position.y;
+            self.stats.score += lines_dropped as u32 * HARD_DROP_POINTS;
+            active_piece.position = drop_position;
             self.active_piece = Some(active_piece);
             self.update_ghost_piece_position();
             self.lock_active_piece();
@@ -246,21 +1133,58 @@ impl<
 
     fn lock_active_piece(&mut self) {
         if let Some(active_piece) = self.active_piece {
-            let is_twist =
+            let finesse_faults = self.finesse_feature.lock(
+                &self.board,
+                &self.piece_set,
+                &self.rules,
+                self.gravity_feature.gravity,
+                &active_piece,
+            );
+            if finesse_faults > 0 {
+                self.stats.finesse_faults += finesse_faults;
+                self.messages.push(Message::FinesseFault(finesse_faults));
+                self.events.push(GameEvent::FinesseFault(finesse_faults));
+                if self.rules.finesse_training {
+                    self.spawn_piece(Some(active_piece.piece_type));
+                    return;
+                }
+            }
+
+            let twist_kind =
                 self.twist_detector
-                    .is_twist(&self.board, &self.piece_set, &active_piece);
+                    .twist_kind(&self.board, &self.piece_set, &active_piece);
+            let is_twist = twist_kind != TwistKind::None;
 
-            let lines_cleared = self.board.lock_piece(
-                self.piece_set
-                    .units(&active_piece.piece_type, &active_piece.rotation),
-                active_piece.position,
-            );
+            let units = self
+                .piece_set
+                .units(&active_piece.piece_type, &active_piece.rotation);
+            let units_above_playfield = units
+                .iter()
+                .filter(|unit| unit.y + active_piece.position.y >= Board::VISIBLE_HEIGHT as i32)
+                .count();
+            let lock_row = units
+                .iter()
+                .map(|unit| (unit.y + active_piece.position.y).max(0) as usize)
+                .min()
+                .unwrap_or_default();
+            let locked_out = match self.rules.lock_out {
+                LockOut::Never => false,
+                LockOut::Entirely => units_above_playfield == units.len(),
+                LockOut::Partially => units_above_playfield > 0,
+            };
+
+            let cleared_rows = self.board.lock_piece(units, active_piece.position);
+            let lines_cleared = cleared_rows.len();
+
+            // Garbage rows are always at the bottom of the board
+            let garbage_cleared = cleared_rows
+                .iter()
+                .filter(|row| **row < self.garbage_rows as usize)
+                .count() as u32;
+            self.garbage_rows -= garbage_cleared;
+            self.stats.garbage_cleared += garbage_cleared;
             match lines_cleared {
-                0 => {
-                    if is_twist {
-                        self.messages.push(Message::Twist(active_piece.piece_type))
-                    }
-                }
+                0 if is_twist => self.messages.push(Message::Twist(active_piece.piece_type)),
                 1 => self.messages.push(if is_twist {
                     Message::TwistSingle(active_piece.piece_type)
                 } else {
@@ -279,16 +1203,202 @@ impl<
                 4 => self.messages.push(Message::Quad),
                 _ => (),
             };
-            if self.board.is_all_clear() {
+            let all_clear = self.board.is_all_clear();
+            if all_clear {
                 self.messages.push(Message::AllClear);
             }
-            self.spawn_piece(None);
+
+            let clear = self
+                .attack_feature
+                .lock(lines_cleared as u32, twist_kind, all_clear);
+            if clear.combo > 0 {
+                self.messages.push(Message::Combo(clear.combo as i32));
+            }
+            self.events.push(GameEvent::PieceLocked(active_piece));
+            if lines_cleared > 0 {
+                self.events.push(GameEvent::LinesCleared(clear));
+            }
+            self.stats.score += self.rules.scoring.clear_score(&clear, self.stats.level);
+            let attack = self.attack_table.attack(&clear);
+            if attack > 0 {
+                self.stats.attack_sent += attack;
+                self.messages.push(Message::Attack(attack));
+            }
+
+            // Attack cancels incoming garbage first, which is only inserted when nothing was
+            // cleared
+            self.outgoing_garbage += self.garbage.cancel(attack);
+            let mut pushed_off = false;
+            if lines_cleared == 0 {
+                let holes = self.garbage.take_rows();
+                pushed_off = self.insert_garbage(holes);
+            }
+            self.stats.pieces_locked += 1;
+            self.stats.lines_cleared += lines_cleared as u32;
+            if is_twist {
+                self.stats.twist_lines_cleared += lines_cleared as u32;
+            }
+            if locked_out || pushed_off {
+                self.end_game(Outcome::ToppedOut);
+                return;
+            }
+            self.active_piece = None;
+            self.run_mode_hook(|mode, context| mode.piece_locked(context, &clear));
+            if self.outcome.is_some() {
+                return;
+            }
             self.hold_feature.reset();
+            let are = self.rules.are.seconds(lock_row, self.stats.level);
+            if are > 0f64 {
+                self.spawn_timer = Some(are);
+                self.update_ghost_piece_position();
+            } else {
+                self.spawn_piece(None);
+            }
+            if self.outcome.is_none() {
+                self.record_history();
+            }
+        }
+    }
+
+    /// Adds the current state to the undo history after a placement, when undo is enabled
+    fn record_history(&mut self) {
+        if self.rules.undo_limit == 0 {
+            return;
+        }
+        let Some(queue) = self.history.copy_queue(&self.queue) else {
+            return;
+        };
+        // The replay isn't rewound by undo, so it doesn't need to be kept in the history
+        match self.capture(queue, ReplayRecorder::new()) {
+            Ok(state) => self.history.record(state, self.rules.undo_limit),
+            // Undoing past a state the mode couldn't save would restore the wrong mode state
+            Err(_) => self.history.clear(),
+        }
+    }
+
+    /// Returns to the state the previous piece was played from
+    fn undo(&mut self) {
+        if let Some(previous) = self.history.undo() {
+            self.restore_history(previous);
+        }
+    }
+
+    /// Returns to the state the last undo took back
+    fn redo(&mut self) {
+        if let Some(next) = self.history.redo() {
+            self.restore_history(next);
+        }
+    }
+
+    /// Restores a state from the undo history, carrying on with the same replay and play time
+    fn restore_history(&mut self, state: GameSnapshot<TRandom>) {
+        let replay_recorder = self.replay_recorder.clone();
+        let time = self.stats.time;
+        // The state was saved by the same mode, so it can always be loaded
+        let _ = self.restore(state);
+        self.replay_recorder = replay_recorder;
+        self.stats.time = time;
+    }
+
+    /// Where the game is in its undo history, or None if undo is disabled
+    pub fn history_position(&self) -> Option<HistoryPosition> {
+        if self.rules.undo_limit == 0 {
+            return None;
+        }
+        self.history.position()
+    }
+
+    fn capture(
+        &self,
+        queue: Queue<PieceType, TRandom>,
+        replay_recorder: ReplayRecorder,
+    ) -> Result<GameSnapshot<TRandom>, SnapshotError> {
+        Ok(GameSnapshot {
+            version: SNAPSHOT_VERSION,
+            rules: self.rules.clone(),
+            mode: self.mode.name().to_string(),
+            mode_state: self.mode.save_state()?,
+            board: self.board.clone(),
+            active_piece: self.active_piece,
+            queue,
+            hold_feature: self.hold_feature.clone(),
+            gravity_feature: self.gravity_feature.clone(),
+            lock_feature: self.lock_feature.clone(),
+            spawn_timer: self.spawn_timer,
+            initial_rotation: self.initial_rotation,
+            garbage: self.garbage.clone(),
+            outgoing_garbage: self.outgoing_garbage,
+            garbage_rows: self.garbage_rows,
+            attack_feature: self.attack_feature.clone(),
+            finesse_feature: self.finesse_feature.clone(),
+            replay_recorder,
+            paused: self.paused,
+            piece_limit: self.piece_limit,
+            pieces_dealt: self.pieces_dealt,
+            stats: self.stats,
+            outcome: self.outcome,
+        })
+    }
+
+    fn restore(&mut self, snapshot: GameSnapshot<TRandom>) -> Result<(), SnapshotError> {
+        self.mode.load_state(snapshot.mode_state)?;
+        self.rules = snapshot.rules;
+        self.board = snapshot.board;
+        self.active_piece = snapshot.active_piece;
+        self.queue = snapshot.queue;
+        self.hold_feature = snapshot.hold_feature;
+        self.gravity_feature = snapshot.gravity_feature;
+        self.lock_feature = snapshot.lock_feature;
+        self.spawn_timer = snapshot.spawn_timer;
+        self.initial_rotation = snapshot.initial_rotation;
+        self.garbage = snapshot.garbage;
+        self.outgoing_garbage = snapshot.outgoing_garbage;
+        self.garbage_rows = snapshot.garbage_rows;
+        self.attack_feature = snapshot.attack_feature;
+        self.finesse_feature = snapshot.finesse_feature;
+        self.replay_recorder = snapshot.replay_recorder;
+        self.paused = snapshot.paused;
+        self.piece_limit = snapshot.piece_limit;
+        self.pieces_dealt = snapshot.pieces_dealt;
+        self.stats = snapshot.stats;
+        self.outcome = snapshot.outcome;
+        self.messages.clear();
+        self.events.clear();
+        self.update_ghost_piece_position();
+        Ok(())
+    }
+
+    fn insert_garbage(&mut self, holes: Vec<usize>) -> bool {
+        insert_garbage(&mut self.board, &mut self.garbage_rows, &holes)
+    }
+
+    /// Pushes the active piece up out of garbage rows raised under it, topping out if it's still
+    /// obstructed after moving up by every row
+    fn raise_active_piece(&mut self, rows: u32) {
+        if let Some(mut active_piece) = self.active_piece {
+            let units = self
+                .piece_set
+                .units(&active_piece.piece_type, &active_piece.rotation);
+            for _ in 0..rows {
+                if !self.board.is_obstructed(units, active_piece.position) {
+                    break;
+                }
+                active_piece.position += Position::up();
+            }
+            if self.board.is_obstructed(units, active_piece.position) {
+                self.end_game(Outcome::ToppedOut);
+                return;
+            }
+            self.active_piece = Some(active_piece);
         }
+        self.update_ghost_piece_position();
     }
 
     fn update_ghost_piece_position(&mut self) {
-        self.ghost_piece_position = if let Some(active_piece) = self.active_piece {
+        self.ghost_piece_position = if !self.rules.ghost_enabled {
+            None
+        } else if let Some(active_piece) = self.active_piece {
             Some(
                 self.board
                     .piece_cast(&self.piece_set, active_piece, Position::down()),
@@ -299,6 +1409,7 @@ impl<
     }
 
     pub fn render(&mut self, delta_time: f64) {
+        let next_piece_types = self.next_piece_types();
         self.renderer.render(
             RenderState::new(
                 self.board.rows.to_vec(),
@@ -306,12 +1417,171 @@ impl<
                 self.active_piece,
                 self.ghost_piece_position,
                 self.hold_feature.hold_piece_type,
-                self.queue.next_items().to_vec(),
+                next_piece_types,
                 self.paused,
                 self.messages.to_vec(),
+                self.garbage.pending(),
+                self.mode.hud(&self.stats),
+                self.stats,
+                self.outcome,
+                self.outcome.map(|outcome| self.mode.outcome_text(outcome)),
+                self.history_position(),
             ),
             delta_time,
         );
         self.messages.clear();
     }
 }
+
+impl<TPieceSet: PieceSet, TRandom: Random<PieceType>, TTwistDetector: TwistDetector<TPieceSet>>
+    HeadlessRustris<TPieceSet, TRandom, TTwistDetector>
+{
+    /// Creates a game that doesn't draw anything or read any input, for bots, tests and servers
+    /// to drive with [step](Self::step)
+    pub fn headless(
+        rules: RuleSet,
+        piece_set: TPieceSet,
+        random: TRandom,
+        twist_detector: TTwistDetector,
+    ) -> Self {
+        Self::new(
+            rules,
+            piece_set,
+            random,
+            NoInputActions,
+            twist_detector,
+            NullRenderer,
+        )
+    }
+}
+
+impl<
+        TPieceSet: PieceSet,
+        TRandom: Random<PieceType> + Clone,
+        TInputActions: InputActions,
+        TRenderer: Renderer<TPieceSet>,
+        TTwistDetector: TwistDetector<TPieceSet>,
+    > Rustris<TPieceSet, TRandom, TInputActions, TRenderer, TTwistDetector>
+{
+    /// Captures the whole simulation state of the game, to be saved and resumed later
+    ///
+    /// Fails if the mode can't save its state.
+    pub fn snapshot(&self) -> Result<GameSnapshot<TRandom>, SnapshotError> {
+        self.capture(self.queue.clone(), self.replay_recorder.clone())
+    }
+
+    /// Resumes a game from a snapshot, which should be done instead of starting it
+    ///
+    /// The game must already be playing the same mode as the snapshot, and takes its rules from
+    /// the snapshot. Calling [init](Self::init) afterwards only initializes the renderer. The
+    /// undo history isn't part of the snapshot, so it starts again from the resumed state.
+    pub fn restore_snapshot(
+        &mut self,
+        snapshot: &GameSnapshot<TRandom>,
+    ) -> Result<(), SnapshotError> {
+        if snapshot.mode != self.mode.name() {
+            return Err(SnapshotError::ModeMismatch {
+                snapshot: snapshot.mode.clone(),
+                game: self.mode.name().to_string(),
+            });
+        }
+        self.restore(snapshot.clone())?;
+        self.started = true;
+        self.history.clear();
+        if self.outcome.is_none() {
+            self.record_history();
+        }
+        Ok(())
+    }
+}
+
+impl<
+        TPieceSet: PieceSet,
+        TRandom: Random<PieceType> + Clone + 'static,
+        TInputActions: InputActions,
+        TRenderer: Renderer<TPieceSet>,
+        TTwistDetector: TwistDetector<TPieceSet>,
+    > Rustris<TPieceSet, TRandom, TInputActions, TRenderer, TTwistDetector>
+{
+    /// Lets placements be taken back when the rules have an undo limit, which should be done
+    /// before the game starts
+    ///
+    /// Undo keeps copies of the randomizer in its history, so it's only available to games
+    /// whose randomizer can be cloned. Replays of games played with undo need it too.
+    pub fn enable_undo(&mut self) {
+        self.history = Box::new(PlacementHistory {
+            states: VecDeque::new(),
+            undone: vec![],
+        });
+    }
+}
+
+impl<
+        TPieceSet: PieceSet,
+        TRandom: Random<PieceType> + Clone,
+        TInputActions: InputActions + Clone,
+        TRenderer: Renderer<TPieceSet>,
+        TTwistDetector: TwistDetector<TPieceSet>,
+    > Rustris<TPieceSet, TRandom, TInputActions, TRenderer, TTwistDetector>
+{
+    /// Captures the current simulation state of the game
+    pub fn keyframe(&self) -> Keyframe<TRandom, TInputActions> {
+        Keyframe {
+            board: self.board.clone(),
+            active_piece: self.active_piece,
+            ghost_piece_position: self.ghost_piece_position,
+            queue: self.queue.clone(),
+            input_actions: self.input_actions.clone(),
+            hold_feature: self.hold_feature.clone(),
+            gravity_feature: self.gravity_feature.clone(),
+            lock_feature: self.lock_feature.clone(),
+            spawn_timer: self.spawn_timer,
+            initial_rotation: self.initial_rotation,
+            garbage: self.garbage.clone(),
+            outgoing_garbage: self.outgoing_garbage,
+            garbage_rows: self.garbage_rows,
+            attack_feature: self.attack_feature.clone(),
+            finesse_feature: self.finesse_feature.clone(),
+            replay_frames: self.replay_recorder.frames(),
+            paused: self.paused,
+            quit: self.quit,
+            mode: self.mode.clone(),
+            pieces_dealt: self.pieces_dealt,
+            stats: self.stats,
+            outcome: self.outcome,
+        }
+    }
+
+    /// Returns the game to the state it was in when the keyframe was captured
+    pub fn restore_keyframe(&mut self, keyframe: &Keyframe<TRandom, TInputActions>) {
+        self.board = keyframe.board.clone();
+        self.active_piece = keyframe.active_piece;
+        self.ghost_piece_position = keyframe.ghost_piece_position;
+        self.queue = keyframe.queue.clone();
+        self.input_actions = keyframe.input_actions.clone();
+        self.hold_feature = keyframe.hold_feature.clone();
+        self.gravity_feature = keyframe.gravity_feature.clone();
+        self.lock_feature = keyframe.lock_feature.clone();
+        self.spawn_timer = keyframe.spawn_timer;
+        self.initial_rotation = keyframe.initial_rotation;
+        self.garbage = keyframe.garbage.clone();
+        self.outgoing_garbage = keyframe.outgoing_garbage;
+        self.garbage_rows = keyframe.garbage_rows;
+        self.attack_feature = keyframe.attack_feature.clone();
+        self.finesse_feature = keyframe.finesse_feature.clone();
+        self.replay_recorder.rewind(keyframe.replay_frames);
+        self.paused = keyframe.paused;
+        self.quit = keyframe.quit;
+        self.mode = keyframe.mode.clone();
+        self.pieces_dealt = keyframe.pieces_dealt;
+        self.stats = keyframe.stats;
+        self.outcome = keyframe.outcome;
+        self.messages.clear();
+        self.events.clear();
+        // Keyframes don't keep the undo history, so it starts again from the restored state
+        self.history.clear();
+        if self.outcome.is_none() {
+            self.record_history();
+        }
+    }
+}
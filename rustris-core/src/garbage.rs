@@ -0,0 +1,169 @@
+use serde::{Deserialize, Serialize};
+
+use super::random::{random_seed, Pcg32};
+
+/// The generator stream used for hole columns ("garbage" in ASCII), so garbage seeded with the
+/// game's seed doesn't follow the same sequence as the piece randomizer
+const GARBAGE_STREAM: u64 = 0x67617262616765;
+
+/// The generator stream for garbage placed by game modes ("modegarb" in ASCII), so it doesn't
+/// repeat the holes of the incoming garbage seeded with the same seed
+pub const MODE_GARBAGE_STREAM: u64 = 0x6d6f646567617262;
+
+/// Garbage lines that have been received but not inserted onto the board yet
+///
+/// Garbage waits in the queue until a piece locks without clearing any lines, and any attack
+/// sent in the meantime cancels the oldest pending lines first. Each inserted row has a single
+/// hole, and [messiness] is the chance that a row's hole is in a different column from the row
+/// below it (0 gives a single clean well, 1 moves the hole on every row).
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GarbageQueue {
+    seed: u64,
+    rng: Pcg32,
+    messiness: f64,
+    pending: Vec<u32>,
+    hole: Option<usize>,
+}
+
+impl GarbageQueue {
+    /// Creates a garbage queue with a new random seed
+    pub fn new(messiness: f64) -> Self {
+        Self::with_seed(messiness, random_seed())
+    }
+
+    pub fn with_seed(messiness: f64, seed: u64) -> Self {
+        Self::with_stream(messiness, seed, GARBAGE_STREAM)
+    }
+
+    /// Creates a garbage queue on one of the generator's streams, e.g. [MODE_GARBAGE_STREAM]
+    pub fn with_stream(messiness: f64, seed: u64, stream: u64) -> Self {
+        Self {
+            seed,
+            rng: Pcg32::with_stream(seed, stream),
+            messiness: messiness.clamp(0f64, 1f64),
+            pending: vec![],
+            hole: None,
+        }
+    }
+
+    /// The seed the hole columns are generated from
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The chance that a row's hole moves from the row below it
+    pub fn messiness(&self) -> f64 {
+        self.messiness
+    }
+
+    /// Adds an attack of the given number of lines to the queue
+    pub fn receive(&mut self, lines: u32) {
+        if lines > 0 {
+            self.pending.push(lines);
+        }
+    }
+
+    /// The total number of lines waiting to be inserted
+    pub fn pending(&self) -> u32 {
+        self.pending.iter().sum()
+    }
+
+    /// Cancels pending lines with an outgoing attack, oldest first
+    ///
+    /// Returns the part of the attack that was left over after everything was cancelled.
+    pub fn cancel(&mut self, attack: u32) -> u32 {
+        let mut attack = attack;
+        while attack > 0 && !self.pending.is_empty() {
+            let cancelled = attack.min(self.pending[0]);
+            attack -= cancelled;
+            self.pending[0] -= cancelled;
+            if self.pending[0] == 0 {
+                self.pending.remove(0);
+            }
+        }
+        attack
+    }
+
+    /// Removes every pending line from the queue, returning the hole column of each row from
+    /// the bottom up
+    pub fn take_rows(&mut self) -> Vec<usize> {
+        let lines = self.pending();
+        self.pending.clear();
+        self.holes(lines)
+    }
+
+    /// Generates the hole columns for rows of garbage that don't go through the queue, e.g.
+    /// garbage placed by a game mode
+    pub fn holes(&mut self, lines: u32) -> Vec<usize> {
+        (0..lines).map(|_| self.next_hole()).collect()
+    }
+
+    fn next_hole(&mut self) -> usize {
+        let hole = match self.hole.filter(|_| !self.roll_messiness()) {
+            Some(hole) => hole,
+            None => self.rng.below(10) as usize,
+        };
+        self.hole = Some(hole);
+        hole
+    }
+
+    fn roll_messiness(&mut self) -> bool {
+        (self.rng.next_u32() as f64 / u32::MAX as f64) < self.messiness
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attack_cancels_the_oldest_garbage_first() {
+        let mut garbage = GarbageQueue::with_seed(0f64, 1);
+        garbage.receive(2);
+        garbage.receive(3);
+        assert_eq!(garbage.pending(), 5);
+        assert_eq!(garbage.cancel(3), 0);
+        assert_eq!(garbage.pending(), 2);
+        assert_eq!(garbage.cancel(4), 2);
+        assert_eq!(garbage.pending(), 0);
+    }
+
+    #[test]
+    fn clean_garbage_keeps_the_same_hole() {
+        let mut garbage = GarbageQueue::with_seed(0f64, 9);
+        garbage.receive(4);
+        let rows = garbage.take_rows();
+        assert_eq!(rows.len(), 4);
+        assert!(rows.iter().all(|hole| *hole == rows[0] && *hole < 10));
+        assert_eq!(garbage.pending(), 0);
+    }
+
+    #[test]
+    fn messy_garbage_moves_the_hole() {
+        let mut garbage = GarbageQueue::with_seed(1f64, 9);
+        garbage.receive(40);
+        let rows = garbage.take_rows();
+        assert!(rows.iter().any(|hole| *hole != rows[0]));
+        assert!(rows.iter().all(|hole| *hole < 10));
+    }
+
+    #[test]
+    fn holes_use_a_different_stream_from_pieces() {
+        assert_ne!(GarbageQueue::with_seed(0f64, 5).rng, Pcg32::new(5));
+        assert_ne!(
+            GarbageQueue::with_seed(0f64, 5).rng,
+            GarbageQueue::with_stream(0f64, 5, MODE_GARBAGE_STREAM).rng
+        );
+    }
+
+    #[test]
+    fn holes_are_deterministic_for_a_seed() {
+        let rows = |seed| {
+            let mut garbage = GarbageQueue::with_seed(0.5, seed);
+            garbage.receive(20);
+            garbage.take_rows()
+        };
+        assert_eq!(rows(3), rows(3));
+        assert_ne!(rows(3), rows(4));
+    }
+}
@@ -1,5 +1,15 @@
+use std::{error::Error, fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    board::Board,
+    piece::{Piece, PieceType},
+    rules::RuleSet,
+};
+
 /// The types of raw input events we care about
-#[derive(Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum Input {
     LeftPress,
     LeftRelease,
@@ -11,7 +21,34 @@ pub enum Input {
     RotateRight,
     HardDrop,
     Hold,
+    Undo,
+    Redo,
     Pause,
+    Quit,
+}
+
+impl FromStr for Input {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "left_press" => Ok(Input::LeftPress),
+            "left_release" => Ok(Input::LeftRelease),
+            "right_press" => Ok(Input::RightPress),
+            "right_release" => Ok(Input::RightRelease),
+            "soft_drop_press" => Ok(Input::SoftDropPress),
+            "soft_drop_release" => Ok(Input::SoftDropRelease),
+            "rotate_left" => Ok(Input::RotateLeft),
+            "rotate_right" => Ok(Input::RotateRight),
+            "hard_drop" => Ok(Input::HardDrop),
+            "hold" => Ok(Input::Hold),
+            "undo" => Ok(Input::Undo),
+            "redo" => Ok(Input::Redo),
+            "pause" => Ok(Input::Pause),
+            "quit" => Ok(Input::Quit),
+            _ => Err(format!("unknown input '{}'", s)),
+        }
+    }
 }
 
 /// A source that generates raw input events
@@ -19,31 +56,224 @@ pub trait InputSource {
     fn inputs(&mut self) -> Vec<Input>;
 }
 
+/// An implementation of InputSource that replays a fixed list of input events
+///
+/// Every call to [inputs] advances the script by one frame and returns the events scheduled
+/// for that frame, in the order they were given. Once every event has been returned, the
+/// source produces no further inputs.
+#[derive(Clone)]
+pub struct ScriptedInputSource {
+    events: Vec<(u64, Input)>,
+    frame: u64,
+    next_event: usize,
+}
+
+impl ScriptedInputSource {
+    /// Creates a source from events scheduled by frame number, where frame 0 is the first call
+    pub fn new(mut events: Vec<(u64, Input)>) -> Self {
+        // A stable sort keeps events on the same frame in the order they were given
+        events.sort_by_key(|(frame, _)| *frame);
+        Self {
+            events,
+            frame: 0,
+            next_event: 0,
+        }
+    }
+
+    /// Creates a source from events scheduled in seconds since the first call
+    ///
+    /// Each time is rounded to the nearest frame of [frame_time] seconds.
+    pub fn from_times(events: Vec<(f64, Input)>, frame_time: f64) -> Self {
+        Self::new(
+            events
+                .into_iter()
+                .map(|(time, input)| (time_to_frame(time, frame_time), input))
+                .collect(),
+        )
+    }
+
+    /// Parses a script where each line schedules one or more inputs
+    ///
+    /// A line starts with a frame number (`12`) or a time in seconds (`0.5s`) followed by the
+    /// names of the inputs to send, e.g. `12 rotate_right hard_drop`. Input names are the
+    /// snake_case names of [Input], and `left`/`right` are shorthand for a tap (a press and a
+    /// release on the same frame). Blank lines and anything after a `#` are ignored.
+    pub fn parse(script: &str, frame_time: f64) -> Result<Self, ScriptError> {
+        let mut events = Vec::new();
+
+        for (index, line) in script.lines().enumerate() {
+            let error = |message: String| ScriptError {
+                line: index + 1,
+                message,
+            };
+
+            let line = line.split('#').next().unwrap_or_default();
+            let mut tokens = line.split_whitespace();
+            let Some(time) = tokens.next() else {
+                continue;
+            };
+
+            let frame = if let Some(seconds) = time.strip_suffix('s') {
+                match seconds.parse::<f64>() {
+                    Ok(seconds) if seconds >= 0f64 => time_to_frame(seconds, frame_time),
+                    _ => return Err(error(format!("invalid time '{}'", time))),
+                }
+            } else {
+                time.parse::<u64>()
+                    .map_err(|_| error(format!("invalid frame '{}'", time)))?
+            };
+
+            let mut has_input = false;
+            for name in tokens {
+                has_input = true;
+                match name {
+                    "left" => {
+                        events.push((frame, Input::LeftPress));
+                        events.push((frame, Input::LeftRelease));
+                    }
+                    "right" => {
+                        events.push((frame, Input::RightPress));
+                        events.push((frame, Input::RightRelease));
+                    }
+                    _ => events.push((frame, name.parse::<Input>().map_err(error)?)),
+                }
+            }
+            if !has_input {
+                return Err(error(format!("no inputs given for '{}'", time)));
+            }
+        }
+
+        Ok(Self::new(events))
+    }
+
+    /// The number of frames that have been played so far
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Returns true once every scripted event has been returned
+    pub fn is_finished(&self) -> bool {
+        self.next_event >= self.events.len()
+    }
+}
+
+impl InputSource for ScriptedInputSource {
+    fn inputs(&mut self) -> Vec<Input> {
+        let mut inputs = Vec::<Input>::new();
+        while let Some((frame, input)) = self.events.get(self.next_event) {
+            if *frame > self.frame {
+                break;
+            }
+            inputs.push(*input);
+            self.next_event += 1;
+        }
+        self.frame += 1;
+        inputs
+    }
+}
+
+fn time_to_frame(time: f64, frame_time: f64) -> u64 {
+    (time / frame_time).round() as u64
+}
+
+/// An error found while parsing an input script
+#[derive(Debug, Eq, PartialEq)]
+pub struct ScriptError {
+    /// The 1-based line number the error was found on
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for ScriptError {}
+
 /// Higher-level input actions that change the game state
-#[derive(Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum Action {
     MoveLeft,
     MoveRight,
+    /// Moves left again because the move key is still held, which isn't a new key press
+    AutoShiftLeft,
+    AutoShiftRight,
     SoftDropStarted,
     SoftDropStopped,
     HardDrop,
     RotateLeft,
     RotateRight,
     Hold,
+    /// Takes back the last placement, when the rules allow undo
+    Undo,
+    /// Plays back a placement that was taken back by undo
+    Redo,
     Pause,
+    Quit,
+    /// Garbage lines sent by an opponent, which arrive even while the game is paused
+    ReceiveGarbage(u32),
+}
+
+/// The settings that control how held movement inputs are turned into repeated move actions
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Handling {
+    pub delayed_auto_shift: f64,
+    pub auto_repeat_rate: f64,
+}
+
+/// What a source of input actions can see of the game before each frame
+pub struct GameView<'a> {
+    pub rules: &'a RuleSet,
+    pub board: &'a Board,
+    pub active_piece: Option<Piece>,
+    pub hold_piece_type: Option<PieceType>,
+    /// Whether the active piece can be held, which it can't if it was just taken out of hold
+    pub can_hold: bool,
+    pub next_piece_types: &'a [PieceType],
+    /// The number of pieces locked so far, which goes up as each piece is placed
+    pub pieces_locked: u32,
+    /// The number of pieces in a row that have cleared lines
+    pub consecutive_clears: u32,
+    /// Whether the last clear was difficult, so the next difficult clear is back-to-back
+    pub back_to_back: bool,
+    /// Whether the game is paused, which drops every action but pause and quit
+    pub paused: bool,
 }
 
 /// A source that generates input actions
 pub trait InputActions {
+    /// Shows the source the game before it generates each frame's actions, for sources that
+    /// decide what to do by looking at the game, like bots
+    fn observe(&mut self, _view: &GameView) {}
+
     fn actions(&mut self, delta_time: f64) -> Vec<Action>;
+
+    /// The handling settings used to generate actions, if there are any
+    fn handling(&self) -> Option<Handling> {
+        None
+    }
+}
+
+/// An implementation of InputActions that never generates any actions, for games that are given
+/// their actions each frame with [crate::game::Rustris::step]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoInputActions;
+
+impl InputActions for NoInputActions {
+    fn actions(&mut self, _: f64) -> Vec<Action> {
+        vec![]
+    }
 }
 
 /// An implementation of InputActions that uses Delayed Auto Shift (DAS)
 ///
 /// If a movement key is pressed, a move action will be generated once.
 /// If the movement key is continually held for longer than [delayed_auto_shift] seconds,
-/// a second move action will be generated. Further move actions will be generated every
+/// an auto shift action will be generated. Further auto shift actions will be generated every
 /// [auto_repeat_rate] seconds until the key is released.
+#[derive(Clone)]
 pub struct DasInputActions<TInputSource: InputSource> {
     input_source: TInputSource,
     delayed_auto_shift: f64,
@@ -56,7 +286,7 @@ pub struct DasInputActions<TInputSource: InputSource> {
 impl<TInputSource: InputSource> DasInputActions<TInputSource> {
     pub fn new(input_source: TInputSource, delayed_auto_shift: f64, auto_repeat_rate: f64) -> Self {
         Self {
-            input_source: input_source,
+            input_source,
             delayed_auto_shift,
             auto_repeat_rate,
             auto_shift_timer: 0f64,
@@ -66,9 +296,9 @@ impl<TInputSource: InputSource> DasInputActions<TInputSource> {
     }
 
     fn handle_auto_shift_timer(&mut self, delta_time: f64) -> i32 {
-        if self.auto_shift_timer > f64::EPSILON {
-            self.auto_shift_timer -= delta_time;
-        }
+        // The timer is always decremented, since a timer that lands within epsilon of zero would
+        // otherwise never trigger an auto shift
+        self.auto_shift_timer -= delta_time;
 
         let mut count = 0;
 
@@ -121,21 +351,190 @@ impl<TInputSource: InputSource> InputActions for DasInputActions<TInputSource> {
         if inputs.contains(&Input::Hold) {
             actions.push(Action::Hold);
         }
+        if inputs.contains(&Input::Undo) {
+            actions.push(Action::Undo);
+        }
+        if inputs.contains(&Input::Redo) {
+            actions.push(Action::Redo);
+        }
         if inputs.contains(&Input::Pause) {
             actions.push(Action::Pause);
         }
+        if inputs.contains(&Input::Quit) {
+            actions.push(Action::Quit);
+        }
         if self.holding_left {
             let count = self.handle_auto_shift_timer(delta_time);
             for _ in 0..count {
-                actions.push(Action::MoveLeft);
+                actions.push(Action::AutoShiftLeft);
             }
         }
         if self.holding_right {
             let count = self.handle_auto_shift_timer(delta_time);
             for _ in 0..count {
-                actions.push(Action::MoveRight);
+                actions.push(Action::AutoShiftRight);
             }
         }
         actions
     }
+
+    fn handling(&self) -> Option<Handling> {
+        Some(Handling {
+            delayed_auto_shift: self.delayed_auto_shift,
+            auto_repeat_rate: self.auto_repeat_rate,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: f64 = 1f64 / 60f64;
+
+    /// Presses right on the first frame and keeps holding it
+    struct HoldRight {
+        pressed: bool,
+    }
+
+    impl InputSource for HoldRight {
+        fn inputs(&mut self) -> Vec<Input> {
+            if std::mem::replace(&mut self.pressed, true) {
+                vec![]
+            } else {
+                vec![Input::RightPress]
+            }
+        }
+    }
+
+    #[test]
+    fn das_auto_repeats_when_the_delay_is_a_whole_number_of_frames() {
+        // Taking whole frames off the delay leaves the timer within epsilon of zero
+        let mut input_actions =
+            DasInputActions::new(HoldRight { pressed: false }, 10f64 * FRAME, 2f64 * FRAME);
+
+        let moves: Vec<usize> = (0..16)
+            .map(|_| input_actions.actions(FRAME).len())
+            .collect();
+        // One move on press, then a move every 2 frames once the DAS delay has passed
+        assert_eq!(moves, vec![1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 1, 0, 1, 0]);
+    }
+
+    fn frames<TInputSource: InputSource>(
+        source: &mut TInputSource,
+        count: usize,
+    ) -> Vec<Vec<Input>> {
+        (0..count).map(|_| source.inputs()).collect()
+    }
+
+    #[test]
+    fn scripted_source_returns_events_on_their_frame() {
+        let mut source = ScriptedInputSource::new(vec![
+            (2, Input::HardDrop),
+            (0, Input::LeftPress),
+            (0, Input::RotateRight),
+        ]);
+
+        assert_eq!(
+            frames(&mut source, 4),
+            vec![
+                vec![Input::LeftPress, Input::RotateRight],
+                vec![],
+                vec![Input::HardDrop],
+                vec![],
+            ]
+        );
+        assert!(source.is_finished());
+        assert_eq!(source.frame(), 4);
+    }
+
+    #[test]
+    fn scripted_source_converts_times_to_frames() {
+        let mut source =
+            ScriptedInputSource::from_times(vec![(0.05, Input::Hold), (0f64, Input::Pause)], FRAME);
+
+        assert_eq!(
+            frames(&mut source, 4),
+            vec![vec![Input::Pause], vec![], vec![], vec![Input::Hold]]
+        );
+    }
+
+    #[test]
+    fn parse_script() {
+        let script = "
+            # tap left twice, then rotate and drop
+            0 left
+            2 left
+            3 rotate_right   # comments can follow inputs
+            0.1s soft_drop_press hard_drop
+        ";
+        let mut source = ScriptedInputSource::parse(script, FRAME).unwrap();
+
+        let inputs = frames(&mut source, 7);
+        assert_eq!(inputs[0], vec![Input::LeftPress, Input::LeftRelease]);
+        assert_eq!(inputs[2], vec![Input::LeftPress, Input::LeftRelease]);
+        assert_eq!(inputs[3], vec![Input::RotateRight]);
+        assert_eq!(inputs[6], vec![Input::SoftDropPress, Input::HardDrop]);
+        assert!(source.is_finished());
+    }
+
+    #[test]
+    fn parse_script_errors() {
+        assert_eq!(
+            ScriptedInputSource::parse("0 left\nsoon hold", FRAME).err(),
+            Some(ScriptError {
+                line: 2,
+                message: "invalid frame 'soon'".to_string()
+            })
+        );
+        assert_eq!(
+            ScriptedInputSource::parse("-1s hold", FRAME)
+                .err()
+                .unwrap()
+                .line,
+            1
+        );
+        assert_eq!(
+            ScriptedInputSource::parse("4 jump", FRAME)
+                .err()
+                .unwrap()
+                .message,
+            "unknown input 'jump'"
+        );
+        assert_eq!(
+            ScriptedInputSource::parse("\n\n4", FRAME)
+                .err()
+                .unwrap()
+                .line,
+            3
+        );
+    }
+
+    #[test]
+    fn das_taps_move_once() {
+        let source = ScriptedInputSource::parse("0 left\n2 left\n3 right", FRAME).unwrap();
+        let mut input_actions = DasInputActions::new(source, 10f64 * FRAME, 2f64 * FRAME);
+
+        let actions: Vec<Vec<Action>> = (0..30).map(|_| input_actions.actions(FRAME)).collect();
+        assert_eq!(actions[0], vec![Action::MoveLeft]);
+        assert_eq!(actions[2], vec![Action::MoveLeft]);
+        assert_eq!(actions[3], vec![Action::MoveRight]);
+        assert_eq!(actions.iter().flatten().count(), 3);
+    }
+
+    #[test]
+    fn das_auto_repeats_while_held() {
+        let source = ScriptedInputSource::parse("0 right_press\n15 right_release", FRAME).unwrap();
+        let mut input_actions = DasInputActions::new(source, 9.5 * FRAME, 2f64 * FRAME);
+
+        let moves: Vec<usize> = (0..20)
+            .map(|_| input_actions.actions(FRAME).len())
+            .collect();
+        // One move on press, then a move every 2 frames once the DAS delay has passed,
+        // counting the press frame itself
+        assert_eq!(
+            moves,
+            vec![1, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 1, 0, 1, 0, 0, 0, 0, 0, 0]
+        );
+    }
 }
@@ -1,9 +1,19 @@
+pub mod attack;
 pub mod board;
+pub mod finesse;
 pub mod game;
+pub mod garbage;
 pub mod input;
+pub mod mode;
 pub mod piece;
+pub mod placement;
 pub mod position;
+pub mod puzzle;
 pub mod queue;
 pub mod random;
 pub mod renderer;
+pub mod replay;
+pub mod rules;
+pub mod score;
+pub mod snapshot;
 pub mod twist;
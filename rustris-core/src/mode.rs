@@ -0,0 +1,825 @@
+use std::{str::FromStr, time::Duration};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    attack::ClearInfo,
+    game::ModeContext,
+    garbage::{GarbageQueue, MODE_GARBAGE_STREAM},
+    puzzle::PuzzleGoal,
+    renderer::HudEntry,
+};
+
+/// The built-in game modes, which can be turned into a [GameMode] to play
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Mode {
+    /// Plays until the player tops out or quits
+    #[default]
+    Endless,
+    /// Ends once the given number of lines have been cleared
+    Sprint { lines: u32 },
+    /// Scores as many points as possible before the time runs out
+    Ultra { duration: Duration },
+    /// Goes up a level every 10 lines, ending once the given number of lines have been cleared
+    /// or never if there's no line goal
+    Marathon { lines: Option<u32> },
+    /// Starts with rows of garbage at the bottom of the board, ending once the given number of
+    /// garbage lines have been cleared
+    /// The board is refilled to keep [rows] rows of garbage until every line has been added.
+    Cheese { lines: u32, rows: u32 },
+    /// Raises a row of garbage every [interval], which gets shorter with every row, and plays
+    /// until the player tops out
+    Survival { interval: Duration },
+    /// Counts levels from 0 to 999, going up for every piece and every line, and grades the
+    /// player once the game ends
+    Arcade,
+    /// Plays a fixed sequence of pieces from a preset board, ending once the goal is reached or
+    /// failing when the pieces run out
+    Puzzle { goal: PuzzleGoal },
+}
+
+impl Mode {
+    pub const SPRINT_LINES: u32 = 40;
+    pub const ULTRA_DURATION: Duration = Duration::from_secs(120);
+    pub const MARATHON_LINES: u32 = 150;
+    pub const CHEESE_LINES: u32 = 100;
+    pub const CHEESE_ROWS: u32 = 10;
+    pub const SURVIVAL_INTERVAL: Duration = Duration::from_secs(5);
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Mode::Endless => "endless",
+            Mode::Sprint { .. } => "sprint",
+            Mode::Ultra { .. } => "ultra",
+            Mode::Marathon { lines: Some(_) } => "marathon",
+            Mode::Marathon { lines: None } => "endless-marathon",
+            Mode::Cheese { .. } => "cheese",
+            Mode::Survival { .. } => "survival",
+            Mode::Arcade => "arcade",
+            Mode::Puzzle { .. } => "puzzle",
+        }
+    }
+
+    /// Creates the game mode that plays by these rules
+    pub fn create(&self) -> Box<dyn GameMode> {
+        match *self {
+            Mode::Endless => Box::new(EndlessMode),
+            Mode::Sprint { lines } => Box::new(SprintMode { lines }),
+            Mode::Ultra { duration } => Box::new(UltraMode { duration }),
+            Mode::Marathon { lines } => Box::new(MarathonMode { lines }),
+            Mode::Cheese { lines, rows } => Box::new(CheeseMode::new(lines, rows)),
+            Mode::Survival { interval } => Box::new(SurvivalMode::new(interval)),
+            Mode::Arcade => Box::new(ArcadeMode::new()),
+            Mode::Puzzle { goal } => Box::new(PuzzleMode::new(goal)),
+        }
+    }
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    /// Parses a mode by name, using the default goal for the mode
+    /// Puzzles can't be parsed, since they need to be loaded from a puzzle file.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "endless" => Ok(Mode::Endless),
+            "sprint" => Ok(Mode::Sprint {
+                lines: Mode::SPRINT_LINES,
+            }),
+            "ultra" => Ok(Mode::Ultra {
+                duration: Mode::ULTRA_DURATION,
+            }),
+            "marathon" => Ok(Mode::Marathon {
+                lines: Some(Mode::MARATHON_LINES),
+            }),
+            "endless-marathon" => Ok(Mode::Marathon { lines: None }),
+            "cheese" => Ok(Mode::Cheese {
+                lines: Mode::CHEESE_LINES,
+                rows: Mode::CHEESE_ROWS,
+            }),
+            "survival" => Ok(Mode::Survival {
+                interval: Mode::SURVIVAL_INTERVAL,
+            }),
+            "arcade" => Ok(Mode::Arcade),
+            _ => Err(format!("unknown mode '{}'", s)),
+        }
+    }
+}
+
+/// How a game ended
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Outcome {
+    /// The goal of the mode was reached
+    Completed,
+    /// A new piece couldn't spawn because the board was filled too high
+    ToppedOut,
+    /// The goal of the mode can no longer be reached
+    Failed,
+}
+
+/// What a game mode decides about the game after each of its hooks
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Verdict {
+    /// The game goes on
+    Continue,
+    /// The goal was reached, so the game ends as completed
+    Win,
+    /// The goal can no longer be reached, so the game ends as failed
+    Lose,
+}
+
+/// The rules of a game, which decide when it ends and what the HUD shows
+///
+/// The game calls the hooks as it's played, passing a [ModeContext] that lets the mode change the
+/// stats, board and gravity, then asks for a [Verdict] after each one. Every mode must be Clone,
+/// which gives it [CloneGameMode] so it can be captured by keyframes. Modes that keep their own
+/// state also save it with [save_state](GameMode::save_state) so it can be captured by snapshots.
+pub trait GameMode: CloneGameMode {
+    fn name(&self) -> &str;
+
+    /// Called once when the game starts, before the first piece spawns
+    fn start(&mut self, _context: &mut ModeContext) {}
+
+    /// Called after a piece locks and the stats have been updated for its clear, before the next
+    /// piece spawns
+    fn piece_locked(&mut self, _context: &mut ModeContext, _clear: &ClearInfo) {}
+
+    /// Called every frame the game is played, after the time has been added to the stats
+    fn tick(&mut self, _context: &mut ModeContext, _delta_time: f64) {}
+
+    fn verdict(&self, stats: &Stats) -> Verdict;
+
+    /// The mode's statistics to show while playing, the time, lines and pieces per second by
+    /// default
+    fn hud(&self, stats: &Stats) -> Vec<HudEntry> {
+        vec![
+            HudEntry::new("TIME", format_time(stats.time)),
+            HudEntry::new("LINES", stats.lines_cleared),
+            HudEntry::new("PPS", format!("{:.2}", stats.pieces_per_second())),
+        ]
+    }
+
+    /// The banner shown over the board once the game has ended
+    fn outcome_text(&self, outcome: Outcome) -> &'static str {
+        match outcome {
+            Outcome::Completed => "FINISHED",
+            Outcome::ToppedOut => "TOP OUT",
+            Outcome::Failed => "FAILED",
+        }
+    }
+
+    /// A sentence describing how the game ended, for printing after the game
+    fn summary(&self, outcome: Outcome, _stats: &Stats) -> String {
+        match outcome {
+            Outcome::Completed => format!("Finished {}", self.name()),
+            Outcome::ToppedOut => "Topped out".to_string(),
+            Outcome::Failed => format!("Failed {}", self.name()),
+        }
+    }
+
+    /// The mode's goal and any state it has built up during the game, for game snapshots
+    fn save_state(&self) -> Result<Value, serde_json::Error> {
+        Ok(Value::Null)
+    }
+
+    /// Replaces the mode's goal and state with ones returned by [save_state](Self::save_state)
+    fn load_state(&mut self, _state: Value) -> Result<(), serde_json::Error> {
+        Ok(())
+    }
+}
+
+/// Saves the whole of a mode as its state, for modes that derive Serialize
+fn save_mode_state<T: Serialize>(mode: &T) -> Result<Value, serde_json::Error> {
+    serde_json::to_value(mode)
+}
+
+/// Loads state saved by [save_mode_state]
+fn load_mode_state<T: DeserializeOwned>(
+    mode: &mut T,
+    state: Value,
+) -> Result<(), serde_json::Error> {
+    *mode = serde_json::from_value(state)?;
+    Ok(())
+}
+
+/// Lets boxed game modes be cloned, implemented for every game mode that is Clone
+pub trait CloneGameMode {
+    fn clone_box(&self) -> Box<dyn GameMode>;
+}
+
+impl<T: GameMode + Clone + 'static> CloneGameMode for T {
+    fn clone_box(&self) -> Box<dyn GameMode> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn GameMode> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+/// Plays until the player tops out or quits
+#[derive(Clone)]
+pub struct EndlessMode;
+
+impl GameMode for EndlessMode {
+    fn name(&self) -> &str {
+        "endless"
+    }
+
+    fn verdict(&self, _stats: &Stats) -> Verdict {
+        Verdict::Continue
+    }
+}
+
+/// Ends once the given number of lines have been cleared
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SprintMode {
+    pub lines: u32,
+}
+
+impl GameMode for SprintMode {
+    fn name(&self) -> &str {
+        "sprint"
+    }
+
+    fn verdict(&self, stats: &Stats) -> Verdict {
+        if stats.lines_cleared >= self.lines {
+            Verdict::Win
+        } else {
+            Verdict::Continue
+        }
+    }
+
+    fn hud(&self, stats: &Stats) -> Vec<HudEntry> {
+        vec![
+            HudEntry::new("TIME", format_time(stats.time)),
+            HudEntry::new("LEFT", self.lines.saturating_sub(stats.lines_cleared)),
+            HudEntry::new("PPS", format!("{:.2}", stats.pieces_per_second())),
+        ]
+    }
+
+    fn save_state(&self) -> Result<Value, serde_json::Error> {
+        save_mode_state(self)
+    }
+
+    fn load_state(&mut self, state: Value) -> Result<(), serde_json::Error> {
+        load_mode_state(self, state)
+    }
+}
+
+/// Scores as many points as possible before the time runs out
+#[derive(Clone, Serialize, Deserialize)]
+pub struct UltraMode {
+    pub duration: Duration,
+}
+
+impl GameMode for UltraMode {
+    fn name(&self) -> &str {
+        "ultra"
+    }
+
+    /// The last frame can go past the end, so the time is clamped to keep results comparable
+    fn tick(&mut self, context: &mut ModeContext, _delta_time: f64) {
+        let stats = context.stats_mut();
+        stats.time = stats.time.min(self.duration);
+    }
+
+    fn verdict(&self, stats: &Stats) -> Verdict {
+        if stats.time >= self.duration {
+            Verdict::Win
+        } else {
+            Verdict::Continue
+        }
+    }
+
+    fn hud(&self, stats: &Stats) -> Vec<HudEntry> {
+        vec![
+            HudEntry::new(
+                "TIME",
+                format_time(self.duration.saturating_sub(stats.time)),
+            ),
+            HudEntry::new("SCORE", stats.score),
+        ]
+    }
+
+    fn outcome_text(&self, outcome: Outcome) -> &'static str {
+        match outcome {
+            Outcome::Completed => "TIME UP",
+            Outcome::ToppedOut => "TOP OUT",
+            Outcome::Failed => "FAILED",
+        }
+    }
+
+    fn save_state(&self) -> Result<Value, serde_json::Error> {
+        save_mode_state(self)
+    }
+
+    fn load_state(&mut self, state: Value) -> Result<(), serde_json::Error> {
+        load_mode_state(self, state)
+    }
+}
+
+/// Goes up a level every 10 lines, ending once the given number of lines have been cleared or
+/// never if there's no line goal
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MarathonMode {
+    pub lines: Option<u32>,
+}
+
+impl GameMode for MarathonMode {
+    fn name(&self) -> &str {
+        match self.lines {
+            Some(_) => "marathon",
+            None => "endless-marathon",
+        }
+    }
+
+    fn piece_locked(&mut self, context: &mut ModeContext, _clear: &ClearInfo) {
+        context.stats_mut().level = 1 + context.stats().lines_cleared / 10;
+    }
+
+    fn verdict(&self, stats: &Stats) -> Verdict {
+        match self.lines {
+            Some(lines) if stats.lines_cleared >= lines => Verdict::Win,
+            _ => Verdict::Continue,
+        }
+    }
+
+    fn hud(&self, stats: &Stats) -> Vec<HudEntry> {
+        vec![
+            HudEntry::new("LEVEL", stats.level),
+            match self.lines {
+                Some(lines) => HudEntry::new("LEFT", lines.saturating_sub(stats.lines_cleared)),
+                None => HudEntry::new("LINES", stats.lines_cleared),
+            },
+            HudEntry::new("SCORE", stats.score),
+        ]
+    }
+
+    fn save_state(&self) -> Result<Value, serde_json::Error> {
+        save_mode_state(self)
+    }
+
+    fn load_state(&mut self, state: Value) -> Result<(), serde_json::Error> {
+        load_mode_state(self, state)
+    }
+}
+
+/// Keeps rows of garbage at the bottom of the board, ending once the given number of garbage
+/// lines have been cleared
+///
+/// The board is refilled to keep [rows] rows of garbage until every line has been added.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CheeseMode {
+    lines: u32,
+    rows: u32,
+    garbage: GarbageQueue,
+}
+
+impl CheeseMode {
+    pub fn new(lines: u32, rows: u32) -> Self {
+        Self {
+            lines,
+            rows,
+            // Seeded from the game when the mode starts
+            garbage: GarbageQueue::with_seed(1f64, 0),
+        }
+    }
+
+    fn refill(&mut self, context: &mut ModeContext) {
+        let garbage_rows = context.garbage_rows();
+        let lines_added = context.stats().garbage_cleared + garbage_rows;
+        let missing_rows = self
+            .rows
+            .saturating_sub(garbage_rows)
+            .min(self.lines.saturating_sub(lines_added));
+        let holes = self.garbage.holes(missing_rows);
+        context.insert_garbage(&holes);
+    }
+}
+
+impl GameMode for CheeseMode {
+    fn name(&self) -> &str {
+        "cheese"
+    }
+
+    fn start(&mut self, context: &mut ModeContext) {
+        // Games with the same seed get the same garbage
+        self.garbage = GarbageQueue::with_stream(1f64, context.seed(), MODE_GARBAGE_STREAM);
+        self.refill(context);
+    }
+
+    fn piece_locked(&mut self, context: &mut ModeContext, _clear: &ClearInfo) {
+        self.refill(context);
+    }
+
+    fn verdict(&self, stats: &Stats) -> Verdict {
+        if stats.garbage_cleared >= self.lines {
+            Verdict::Win
+        } else {
+            Verdict::Continue
+        }
+    }
+
+    fn hud(&self, stats: &Stats) -> Vec<HudEntry> {
+        vec![
+            HudEntry::new("TIME", format_time(stats.time)),
+            HudEntry::new("LEFT", self.lines.saturating_sub(stats.garbage_cleared)),
+            HudEntry::new("PPS", format!("{:.2}", stats.pieces_per_second())),
+        ]
+    }
+
+    fn save_state(&self) -> Result<Value, serde_json::Error> {
+        save_mode_state(self)
+    }
+
+    fn load_state(&mut self, state: Value) -> Result<(), serde_json::Error> {
+        load_mode_state(self, state)
+    }
+}
+
+/// Raises a row of garbage every interval, which gets shorter with every row, and plays until
+/// the player tops out
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SurvivalMode {
+    garbage: GarbageQueue,
+    timer: f64,
+    interval: f64,
+}
+
+impl SurvivalMode {
+    /// Each row of garbage shortens the interval to this fraction of what it was
+    const ACCELERATION: f64 = 0.97;
+    const MIN_INTERVAL: f64 = 0.5;
+
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            // Seeded from the game when the mode starts
+            garbage: GarbageQueue::with_seed(1f64, 0),
+            timer: 0f64,
+            interval: interval.as_secs_f64(),
+        }
+    }
+}
+
+impl GameMode for SurvivalMode {
+    fn name(&self) -> &str {
+        "survival"
+    }
+
+    fn start(&mut self, context: &mut ModeContext) {
+        self.garbage = GarbageQueue::with_stream(1f64, context.seed(), MODE_GARBAGE_STREAM);
+    }
+
+    fn tick(&mut self, context: &mut ModeContext, delta_time: f64) {
+        self.timer += delta_time;
+        let mut rows = 0;
+        while self.timer >= self.interval {
+            self.timer -= self.interval;
+            self.interval = (self.interval * Self::ACCELERATION).max(Self::MIN_INTERVAL);
+            rows += 1;
+        }
+        let holes = self.garbage.holes(rows);
+        context.insert_garbage(&holes);
+    }
+
+    fn verdict(&self, _stats: &Stats) -> Verdict {
+        Verdict::Continue
+    }
+
+    fn summary(&self, outcome: Outcome, stats: &Stats) -> String {
+        match outcome {
+            Outcome::ToppedOut => format!("Survived {}", format_time(stats.time)),
+            _ => format!("Finished {}", self.name()),
+        }
+    }
+
+    fn save_state(&self) -> Result<Value, serde_json::Error> {
+        save_mode_state(self)
+    }
+
+    fn load_state(&mut self, state: Value) -> Result<(), serde_json::Error> {
+        load_mode_state(self, state)
+    }
+}
+
+/// Plays a fixed sequence of pieces from a preset board, ending once the goal is reached
+///
+/// The game itself fails the puzzle when the pieces run out.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PuzzleMode {
+    goal: PuzzleGoal,
+    all_cleared: bool,
+}
+
+impl PuzzleMode {
+    pub fn new(goal: PuzzleGoal) -> Self {
+        Self {
+            goal,
+            all_cleared: false,
+        }
+    }
+}
+
+impl GameMode for PuzzleMode {
+    fn name(&self) -> &str {
+        "puzzle"
+    }
+
+    fn piece_locked(&mut self, _context: &mut ModeContext, clear: &ClearInfo) {
+        self.all_cleared |= clear.all_clear;
+    }
+
+    fn verdict(&self, stats: &Stats) -> Verdict {
+        let solved = match self.goal {
+            PuzzleGoal::AllClear => self.all_cleared,
+            PuzzleGoal::Lines(lines) => stats.lines_cleared >= lines,
+            PuzzleGoal::TwistLines(lines) => stats.twist_lines_cleared >= lines,
+            PuzzleGoal::Attack(lines) => stats.attack_sent >= lines,
+        };
+        if solved {
+            Verdict::Win
+        } else {
+            Verdict::Continue
+        }
+    }
+
+    fn hud(&self, stats: &Stats) -> Vec<HudEntry> {
+        let (label, progress, target) = match self.goal {
+            PuzzleGoal::AllClear => return vec![HudEntry::new("GOAL", "ALL CLEAR")],
+            PuzzleGoal::Lines(lines) => ("LINES", stats.lines_cleared, lines),
+            PuzzleGoal::TwistLines(lines) => ("TWIST", stats.twist_lines_cleared, lines),
+            PuzzleGoal::Attack(lines) => ("SEND", stats.attack_sent, lines),
+        };
+        vec![
+            HudEntry::new("GOAL", ""),
+            HudEntry::new(label, format!("{}/{}", progress.min(target), target)),
+        ]
+    }
+
+    fn save_state(&self) -> Result<Value, serde_json::Error> {
+        save_mode_state(self)
+    }
+
+    fn load_state(&mut self, state: Value) -> Result<(), serde_json::Error> {
+        load_mode_state(self, state)
+    }
+}
+
+/// Counts levels from 0 to 999 in sections of 100, going up a level for every piece and every
+/// cleared line, and grades the player on their score when the game ends
+///
+/// Pieces on their own can't take the level past the last level of a section, only clears can.
+/// The grand master grade also needs the game to be finished quickly enough, with a high enough
+/// score at every checkpoint. This is meant to be played with the arcade rules, whose gravity
+/// and delays follow the level.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ArcadeMode {
+    grand_master_eligible: bool,
+}
+
+impl ArcadeMode {
+    pub const MAX_LEVEL: u32 = 999;
+
+    /// Every grade below grand master, from lowest to highest, with the score it needs
+    const GRADES: [(&'static str, u32); 18] = [
+        ("9", 0),
+        ("8", 400),
+        ("7", 800),
+        ("6", 1400),
+        ("5", 2000),
+        ("4", 3500),
+        ("3", 5500),
+        ("2", 8000),
+        ("1", 12000),
+        ("S1", 16000),
+        ("S2", 22000),
+        ("S3", 30000),
+        ("S4", 40000),
+        ("S5", 52000),
+        ("S6", 66000),
+        ("S7", 82000),
+        ("S8", 100000),
+        ("S9", 120000),
+    ];
+
+    /// The levels the grand master grade checks, with the time they must be reached by and the
+    /// score needed by then
+    const GRAND_MASTER_CHECKPOINTS: [(u32, Duration, u32); 3] = [
+        (300, Duration::from_secs(4 * 60 + 15), 12000),
+        (500, Duration::from_secs(7 * 60 + 30), 40000),
+        (999, Duration::from_secs(13 * 60 + 30), 126000),
+    ];
+
+    pub fn new() -> Self {
+        Self {
+            grand_master_eligible: true,
+        }
+    }
+
+    /// The level after a piece locks on the given level and clears the given number of lines
+    pub fn next_level(level: u32, lines: u32) -> u32 {
+        let level = if level % 100 == 99 || level == Self::MAX_LEVEL - 1 {
+            level
+        } else {
+            level + 1
+        };
+        (level + lines).min(Self::MAX_LEVEL)
+    }
+
+    /// The grade earned so far
+    pub fn grade(&self, stats: &Stats) -> &'static str {
+        if self.grand_master_eligible && stats.level >= Self::MAX_LEVEL {
+            return "GM";
+        }
+        Self::GRADES
+            .iter()
+            .rev()
+            .find(|(_, score)| stats.score >= *score)
+            .map_or("9", |(grade, _)| grade)
+    }
+}
+
+impl Default for ArcadeMode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GameMode for ArcadeMode {
+    fn name(&self) -> &str {
+        "arcade"
+    }
+
+    fn start(&mut self, context: &mut ModeContext) {
+        context.stats_mut().level = 0;
+    }
+
+    fn piece_locked(&mut self, context: &mut ModeContext, clear: &ClearInfo) {
+        let stats = context.stats_mut();
+        let previous_level = stats.level;
+        stats.level = Self::next_level(previous_level, clear.lines);
+
+        for (level, time, score) in Self::GRAND_MASTER_CHECKPOINTS {
+            if previous_level < level
+                && stats.level >= level
+                && (stats.time > time || stats.score < score)
+            {
+                self.grand_master_eligible = false;
+            }
+        }
+    }
+
+    fn verdict(&self, stats: &Stats) -> Verdict {
+        if stats.level >= Self::MAX_LEVEL {
+            Verdict::Win
+        } else {
+            Verdict::Continue
+        }
+    }
+
+    fn hud(&self, stats: &Stats) -> Vec<HudEntry> {
+        let section_end = ((stats.level / 100 + 1) * 100).min(Self::MAX_LEVEL);
+        vec![
+            HudEntry::new("LEVEL", stats.level),
+            HudEntry::new("NEXT", section_end),
+            HudEntry::new("GRADE", self.grade(stats)),
+            HudEntry::new("TIME", format_time(stats.time)),
+        ]
+    }
+
+    fn summary(&self, outcome: Outcome, stats: &Stats) -> String {
+        match outcome {
+            Outcome::Completed => format!("Finished arcade with grade {}", self.grade(stats)),
+            _ => format!("Topped out with grade {}", self.grade(stats)),
+        }
+    }
+
+    fn save_state(&self) -> Result<Value, serde_json::Error> {
+        save_mode_state(self)
+    }
+
+    fn load_state(&mut self, state: Value) -> Result<(), serde_json::Error> {
+        load_mode_state(self, state)
+    }
+}
+
+/// Statistics about the game played so far
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Stats {
+    /// Scores are multiplied by the level and gravity speeds up with it, but it only goes up in
+    /// marathon and arcade games
+    pub level: u32,
+    pub lines_cleared: u32,
+    /// The number of cleared lines that were garbage
+    pub garbage_cleared: u32,
+    /// The number of lines cleared by twist clears
+    pub twist_lines_cleared: u32,
+    /// The number of garbage lines sent by clears, including lines that only cancelled
+    /// incoming garbage
+    pub attack_sent: u32,
+    pub pieces_locked: u32,
+    /// The number of key presses more than each piece needed to reach where it was placed
+    pub finesse_faults: u32,
+    pub score: u32,
+    /// The time spent playing, which only advances while the game is updated and not paused
+    pub time: Duration,
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self {
+            level: 1,
+            lines_cleared: 0,
+            garbage_cleared: 0,
+            twist_lines_cleared: 0,
+            attack_sent: 0,
+            pieces_locked: 0,
+            finesse_faults: 0,
+            score: 0,
+            time: Duration::ZERO,
+        }
+    }
+}
+
+impl Stats {
+    pub fn pieces_per_second(&self) -> f64 {
+        if self.time.is_zero() {
+            0f64
+        } else {
+            self.pieces_locked as f64 / self.time.as_secs_f64()
+        }
+    }
+}
+
+/// Formats a duration as minutes, seconds and milliseconds, e.g. 1:05.250
+pub fn format_time(time: Duration) -> String {
+    let millis = time.as_millis();
+    format!(
+        "{}:{:02}.{:03}",
+        millis / 60_000,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saving_state_that_is_not_json_fails() {
+        // JSON objects can only have string keys
+        let state = std::collections::HashMap::from([((1, 2), 3)]);
+        assert!(save_mode_state(&state).is_err());
+    }
+
+    #[test]
+    fn formats_times() {
+        assert_eq!(format_time(Duration::from_micros(65_250_900)), "1:05.250");
+        assert_eq!(format_time(Duration::ZERO), "0:00.000");
+    }
+
+    #[test]
+    fn arcade_levels_stop_at_the_end_of_each_section() {
+        assert_eq!(ArcadeMode::next_level(0, 0), 1);
+        assert_eq!(ArcadeMode::next_level(10, 4), 15);
+        assert_eq!(ArcadeMode::next_level(98, 0), 99);
+        assert_eq!(ArcadeMode::next_level(99, 0), 99);
+        assert_eq!(ArcadeMode::next_level(99, 1), 100);
+        assert_eq!(ArcadeMode::next_level(998, 0), 998);
+        assert_eq!(ArcadeMode::next_level(997, 4), 999);
+    }
+
+    #[test]
+    fn arcade_grades_follow_the_score() {
+        let stats = |level, score| Stats {
+            level,
+            score,
+            ..Stats::default()
+        };
+        let mode = ArcadeMode::new();
+        assert_eq!(mode.grade(&stats(0, 0)), "9");
+        assert_eq!(mode.grade(&stats(100, 12000)), "1");
+        assert_eq!(mode.grade(&stats(500, 130000)), "S9");
+        assert_eq!(mode.grade(&stats(999, 130000)), "GM");
+
+        let mode = ArcadeMode {
+            grand_master_eligible: false,
+        };
+        assert_eq!(mode.grade(&stats(999, 130000)), "S9");
+    }
+
+    #[test]
+    fn parses_modes() {
+        assert_eq!("sprint".parse(), Ok(Mode::Sprint { lines: 40 }));
+        assert_eq!(
+            "endless-marathon".parse(),
+            Ok(Mode::Marathon { lines: None })
+        );
+        assert!("race".parse::<Mode>().is_err());
+    }
+}
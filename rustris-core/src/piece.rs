@@ -1,6 +1,10 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
 use super::{board::Board, position::Position};
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum PieceType {
     I,
     T,
@@ -25,12 +29,31 @@ impl PieceType {
     }
 }
 
+impl FromStr for PieceType {
+    type Err = String;
+
+    /// Parses a piece type from its letter, e.g. "T"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().as_str() {
+            "I" => Ok(PieceType::I),
+            "T" => Ok(PieceType::T),
+            "O" => Ok(PieceType::O),
+            "J" => Ok(PieceType::J),
+            "L" => Ok(PieceType::L),
+            "Z" => Ok(PieceType::Z),
+            "S" => Ok(PieceType::S),
+            _ => Err(format!("unknown piece '{}'", s)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum Direction {
     CW,
     CCW,
 }
 
-#[derive(Eq, PartialEq, Hash, Copy, Clone)]
+#[derive(Eq, PartialEq, Hash, Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum Rotation {
     Up,
     Right,
@@ -57,7 +80,7 @@ impl Rotation {
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Piece {
     pub piece_type: PieceType,
     pub rotation: Rotation,
@@ -0,0 +1,95 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::{
+    board::Board,
+    piece::{Direction, Piece, PieceSet},
+    position::Position,
+    twist::{TwistDetector, TwistKind},
+};
+
+/// A place a piece can be locked, found by [placements]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Placement {
+    /// The piece resting where it would lock
+    pub piece: Piece,
+    /// How the twist detector sees the piece locking there
+    pub twist: TwistKind,
+}
+
+impl Placement {
+    /// The board cells the piece fills, from the bottom left
+    pub fn cells(&self, piece_set: &dyn PieceSet) -> [Position; 4] {
+        piece_cells(piece_set, &self.piece)
+    }
+}
+
+/// Finds every place the piece can be locked by moving, rotating and soft dropping it from where
+/// it is, including placements that can only be reached by tucking under overhangs or kicking
+/// into slots
+///
+/// Placements that fill the same cells in different rotations, e.g. an O piece in each rotation,
+/// are only returned once, as whichever takes the fewest moves to reach. Nothing is returned if
+/// the piece is already obstructed.
+pub fn placements<TPieceSet: PieceSet, TTwistDetector: TwistDetector<TPieceSet>>(
+    board: &Board,
+    piece_set: &TPieceSet,
+    twist_detector: &TTwistDetector,
+    piece: Piece,
+) -> Vec<Placement> {
+    if board.is_obstructed(
+        piece_set.units(&piece.piece_type, &piece.rotation),
+        piece.position,
+    ) {
+        return vec![];
+    }
+
+    let mut placements = vec![];
+    let mut placed_cells = HashSet::new();
+    let mut visited = HashSet::from([(piece.position, piece.rotation)]);
+    let mut queue = VecDeque::from([piece]);
+    while let Some(piece) = queue.pop_front() {
+        let units = piece_set.units(&piece.piece_type, &piece.rotation);
+        if board.is_obstructed(units, piece.position + Position::down())
+            && placed_cells.insert(piece_cells(piece_set, &piece))
+        {
+            placements.push(Placement {
+                piece,
+                twist: twist_detector.twist_kind(board, piece_set, &piece),
+            });
+        }
+
+        for next in next_pieces(board, piece_set, &piece) {
+            if visited.insert((next.position, next.rotation)) {
+                queue.push_back(next);
+            }
+        }
+    }
+    placements
+}
+
+/// The pieces that one move, rotation or soft drop step can turn the piece into
+fn next_pieces(board: &Board, piece_set: &dyn PieceSet, piece: &Piece) -> Vec<Piece> {
+    let units = piece_set.units(&piece.piece_type, &piece.rotation);
+    let mut pieces: Vec<Piece> = [Position::left(), Position::right(), Position::down()]
+        .iter()
+        .map(|offset| Piece {
+            position: piece.position + *offset,
+            ..*piece
+        })
+        .filter(|moved| !board.is_obstructed(units, moved.position))
+        .collect();
+    pieces.extend(
+        [Direction::CW, Direction::CCW]
+            .iter()
+            .filter_map(|direction| piece_set.rotate_piece(board, piece, *direction)),
+    );
+    pieces
+}
+
+pub(crate) fn piece_cells(piece_set: &dyn PieceSet, piece: &Piece) -> [Position; 4] {
+    let mut cells = piece_set
+        .units(&piece.piece_type, &piece.rotation)
+        .map(|unit| unit + piece.position);
+    cells.sort_by_key(|cell| (cell.y, cell.x));
+    cells
+}
@@ -0,0 +1,242 @@
+use std::{error::Error, fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{board::Board, piece::PieceType, random::SequenceRandom};
+
+/// What has to be done to solve a puzzle
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum PuzzleGoal {
+    /// Clear every block from the board
+    AllClear,
+    /// Clear the given number of lines
+    Lines(u32),
+    /// Clear the given number of lines with twist clears
+    TwistLines(u32),
+    /// Send the given number of garbage lines
+    Attack(u32),
+}
+
+impl fmt::Display for PuzzleGoal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PuzzleGoal::AllClear => write!(f, "all-clear"),
+            PuzzleGoal::Lines(lines) => write!(f, "lines {}", lines),
+            PuzzleGoal::TwistLines(lines) => write!(f, "twist-lines {}", lines),
+            PuzzleGoal::Attack(lines) => write!(f, "attack {}", lines),
+        }
+    }
+}
+
+impl FromStr for PuzzleGoal {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words: Vec<&str> = s.split_whitespace().collect();
+        let count = || match words.get(1).map(|word| word.parse::<u32>()) {
+            Some(Ok(count)) if count > 0 && words.len() == 2 => Ok(count),
+            _ => Err(format!("goal '{}' needs a single positive count", words[0])),
+        };
+        match words.first() {
+            Some(&"all-clear") if words.len() == 1 => Ok(PuzzleGoal::AllClear),
+            Some(&"lines") => Ok(PuzzleGoal::Lines(count()?)),
+            Some(&"twist-lines") => Ok(PuzzleGoal::TwistLines(count()?)),
+            Some(&"attack") => Ok(PuzzleGoal::Attack(count()?)),
+            _ => Err(format!("unknown goal '{}'", s)),
+        }
+    }
+}
+
+/// A starting board with a fixed sequence of pieces and a goal to reach with them
+///
+/// Puzzles are written as text, where lines starting with `# ` are comments:
+///
+/// ```text
+/// name: Fill the gap
+/// pieces: T I
+/// hold: O
+/// goal: lines 2
+/// board:
+/// ##...#####
+/// ###.######
+/// ```
+///
+/// The board is drawn with the top row first, using `#` for filled cells and `.` for empty
+/// ones, and must be the last section. The name and hold piece are optional.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Puzzle {
+    pub name: Option<String>,
+    pub board: Board,
+    pub pieces: Vec<PieceType>,
+    pub hold: Option<PieceType>,
+    pub goal: PuzzleGoal,
+}
+
+impl Puzzle {
+    /// Creates a randomizer that deals the puzzle's pieces in order
+    pub fn random(&self) -> SequenceRandom<PieceType> {
+        SequenceRandom::new(self.pieces.to_vec())
+    }
+
+    pub fn parse(puzzle: &str) -> Result<Self, PuzzleError> {
+        let mut name = None;
+        let mut pieces = None;
+        let mut hold = None;
+        let mut goal = None;
+        let mut board_rows: Option<Vec<[bool; 10]>> = None;
+
+        for (index, line) in puzzle.lines().enumerate() {
+            let error = |message: String| PuzzleError {
+                line: index + 1,
+                message,
+            };
+
+            // Board rows start with # too, so comments need a space after the #
+            let line = line.trim();
+            if line.is_empty() || line == "#" || line.starts_with("# ") {
+                continue;
+            }
+
+            if let Some(rows) = &mut board_rows {
+                if line.len() != 10 || !line.chars().all(|cell| cell == '#' || cell == '.') {
+                    return Err(error(format!(
+                        "board rows must be 10 cells of '#' or '.', found '{}'",
+                        line
+                    )));
+                }
+                let mut row = [false; 10];
+                for (x, cell) in line.chars().enumerate() {
+                    row[x] = cell == '#';
+                }
+                rows.push(row);
+                continue;
+            }
+
+            let (key, value) = match line.split_once(':') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => return Err(error(format!("expected 'key: value', found '{}'", line))),
+            };
+            match key {
+                "name" => name = Some(value.to_string()),
+                "pieces" => {
+                    let parsed = value
+                        .split_whitespace()
+                        .map(|piece| piece.parse::<PieceType>())
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(error)?;
+                    if parsed.is_empty() {
+                        return Err(error("a puzzle needs at least one piece".to_string()));
+                    }
+                    pieces = Some(parsed);
+                }
+                "hold" => hold = Some(value.parse::<PieceType>().map_err(error)?),
+                "goal" => goal = Some(value.parse::<PuzzleGoal>().map_err(error)?),
+                "board" => board_rows = Some(vec![]),
+                _ => return Err(error(format!("unknown key '{}'", key))),
+            }
+        }
+
+        let last_line = puzzle.lines().count();
+        let missing = |key: &str| PuzzleError {
+            line: last_line,
+            message: format!("missing '{}'", key),
+        };
+        let pieces = pieces.ok_or_else(|| missing("pieces"))?;
+        let goal = goal.ok_or_else(|| missing("goal"))?;
+        let board_rows = board_rows.ok_or_else(|| missing("board"))?;
+
+        let mut board = Board::new();
+        if board_rows.len() > 20 {
+            return Err(PuzzleError {
+                line: last_line,
+                message: "the board can't be more than 20 rows high".to_string(),
+            });
+        }
+        for (y, row) in board_rows.iter().rev().enumerate() {
+            board.rows[y] = *row;
+        }
+
+        Ok(Self {
+            name,
+            board,
+            pieces,
+            hold,
+            goal,
+        })
+    }
+}
+
+/// An error found while parsing a puzzle
+#[derive(Debug, Eq, PartialEq)]
+pub struct PuzzleError {
+    /// The 1-based line number the error was found on
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for PuzzleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for PuzzleError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUZZLE: &str = "
+        # A T-spin double with an O to spare
+        name: TSD
+        pieces: T o
+        hold: I
+        goal: twist-lines 2
+        board:
+        ##...#####
+        ###.######
+        ";
+
+    #[test]
+    fn parses_puzzles() {
+        let puzzle = Puzzle::parse(PUZZLE).unwrap();
+        assert_eq!(puzzle.name, Some("TSD".to_string()));
+        assert_eq!(puzzle.pieces, vec![PieceType::T, PieceType::O]);
+        assert_eq!(puzzle.hold, Some(PieceType::I));
+        assert_eq!(puzzle.goal, PuzzleGoal::TwistLines(2));
+        assert_eq!(
+            puzzle.board.rows[0],
+            [true, true, true, false, true, true, true, true, true, true]
+        );
+        assert_eq!(
+            puzzle.board.rows[1],
+            [true, true, false, false, false, true, true, true, true, true]
+        );
+        assert!(puzzle.board.rows[2].iter().all(|cell| !cell));
+    }
+
+    #[test]
+    fn parses_goals() {
+        assert_eq!("all-clear".parse(), Ok(PuzzleGoal::AllClear));
+        assert_eq!("attack 4".parse(), Ok(PuzzleGoal::Attack(4)));
+        assert_eq!("lines 3".parse(), Ok(PuzzleGoal::Lines(3)));
+        assert!("lines".parse::<PuzzleGoal>().is_err());
+        assert!("attack 0".parse::<PuzzleGoal>().is_err());
+        assert!("win".parse::<PuzzleGoal>().is_err());
+    }
+
+    #[test]
+    fn reports_errors_with_line_numbers() {
+        let error = Puzzle::parse("pieces: T X\ngoal: all-clear\nboard:").err();
+        assert_eq!(error.unwrap().line, 1);
+
+        let error = Puzzle::parse("pieces: T\ngoal: all-clear\nboard:\n###").err();
+        assert_eq!(error.unwrap().line, 4);
+
+        let error = Puzzle::parse("pieces: T\nboard:").err();
+        assert_eq!(error.unwrap().message, "missing 'goal'");
+
+        let error = Puzzle::parse("pieces:\ngoal: all-clear\nboard:").err();
+        assert_eq!(error.unwrap().message, "a puzzle needs at least one piece");
+    }
+}
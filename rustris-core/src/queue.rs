@@ -1,5 +1,8 @@
+use serde::{Deserialize, Serialize};
+
 use super::random::Random;
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Queue<T: Clone, TRandom: Random<T>> {
     random: TRandom,
     size: usize,
@@ -15,6 +18,7 @@ impl<T: Clone, TRandom: Random<T>> Queue<T, TRandom> {
         }
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> T {
         self.populate_next_items();
         let item = self.next_items.remove(0);
@@ -22,6 +26,15 @@ impl<T: Clone, TRandom: Random<T>> Queue<T, TRandom> {
         item
     }
 
+    pub fn random(&self) -> &TRandom {
+        &self.random
+    }
+
+    /// The number of items shown in the queue
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
     pub fn next_items(&mut self) -> &Vec<T> {
         self.populate_next_items();
         &self.next_items
@@ -1,20 +1,113 @@
-use rand::seq::SliceRandom;
-use rand::thread_rng;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::piece::PieceType;
 
 pub trait Random<T: Clone> {
     fn next(&mut self) -> T;
+
+    /// The seed the sequence was generated from, if it was generated from one
+    fn seed(&self) -> Option<u64> {
+        None
+    }
+}
+
+/// Picks a new seed from the operating system's source of randomness
+pub fn random_seed() -> u64 {
+    rand::random()
+}
+
+/// A small pseudorandom number generator (PCG-XSH-RR with 64-bit state and 32-bit output)
+///
+/// Every randomizer is built on this generator instead of the rand crate's generators, which
+/// don't guarantee the same output across versions or platforms. The sequence produced for a
+/// seed must never change, otherwise seeds that have been shared or stored would deal different
+/// pieces.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Pcg32 {
+    state: u64,
+    increment: u64,
 }
 
+impl Pcg32 {
+    const MULTIPLIER: u64 = 6364136223846793005;
+    const DEFAULT_STREAM: u64 = 0x5851f42d4c957f2d;
+
+    pub fn new(seed: u64) -> Self {
+        Self::with_stream(seed, Self::DEFAULT_STREAM)
+    }
+
+    /// Creates a generator on one of its 2^63 streams, which produce unrelated sequences for the
+    /// same seed
+    pub fn with_stream(seed: u64, stream: u64) -> Self {
+        let mut rng = Self {
+            state: 0,
+            increment: (stream << 1) | 1,
+        };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+        rng
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let state = self.state;
+        self.state = state
+            .wrapping_mul(Self::MULTIPLIER)
+            .wrapping_add(self.increment);
+        let xorshifted = (((state >> 18) ^ state) >> 27) as u32;
+        let rotation = (state >> 59) as u32;
+        xorshifted.rotate_right(rotation)
+    }
+
+    /// Returns a uniformly distributed number in 0..bound, which panics if the range is empty
+    pub fn below(&mut self, bound: u32) -> u32 {
+        assert!(bound > 0, "the bound must be at least 1");
+        // Values below the threshold are rejected so every result is equally likely
+        let threshold = bound.wrapping_neg() % bound;
+        loop {
+            let value = self.next_u32();
+            if value >= threshold {
+                return value % bound;
+            }
+        }
+    }
+
+    /// Shuffles the values in place using a Fisher-Yates shuffle
+    pub fn shuffle<T>(&mut self, values: &mut [T]) {
+        for i in (1..values.len()).rev() {
+            let j = self.below(i as u32 + 1) as usize;
+            values.swap(i, j);
+        }
+    }
+}
+
+/// A Random implementation that deals every value once in a shuffled order before
+/// reshuffling them into a new bag
+#[derive(Clone, Serialize, Deserialize)]
 pub struct RandomBag<T: Clone> {
+    seed: u64,
+    rng: Pcg32,
     original_values: Vec<T>,
     current_values: Vec<T>,
 }
 
 impl<T: Clone> RandomBag<T> {
+    /// Creates a bag with a new random seed
     pub fn new(values: Vec<T>) -> Self {
+        Self::with_seed(values, random_seed())
+    }
+
+    /// Panics if there are no values to deal
+    pub fn with_seed(values: Vec<T>, seed: u64) -> Self {
+        assert!(!values.is_empty(), "a bag needs at least one value");
+        let mut rng = Pcg32::new(seed);
         let mut current_values = values.to_vec();
-        current_values.shuffle(&mut thread_rng());
+        rng.shuffle(&mut current_values);
         Self {
+            seed,
+            rng,
             original_values: values,
             current_values,
         }
@@ -27,8 +120,417 @@ impl<T: Clone> Random<T> for RandomBag<T> {
             value
         } else {
             self.current_values = self.original_values.to_vec();
-            self.current_values.shuffle(&mut thread_rng());
+            self.rng.shuffle(&mut self.current_values);
             self.next()
         }
     }
+
+    fn seed(&self) -> Option<u64> {
+        Some(self.seed)
+    }
+}
+
+/// A Random implementation where every value is equally likely, regardless of what was dealt
+/// before
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PureRandom<T: Clone> {
+    seed: u64,
+    rng: Pcg32,
+    values: Vec<T>,
+}
+
+impl<T: Clone> PureRandom<T> {
+    /// Creates a randomizer with a new random seed
+    pub fn new(values: Vec<T>) -> Self {
+        Self::with_seed(values, random_seed())
+    }
+
+    /// Panics if there are no values to deal
+    pub fn with_seed(values: Vec<T>, seed: u64) -> Self {
+        assert!(!values.is_empty(), "a randomizer needs at least one value");
+        Self {
+            seed,
+            rng: Pcg32::new(seed),
+            values,
+        }
+    }
+}
+
+impl<T: Clone> Random<T> for PureRandom<T> {
+    fn next(&mut self) -> T {
+        self.values[self.rng.below(self.values.len() as u32) as usize].clone()
+    }
+
+    fn seed(&self) -> Option<u64> {
+        Some(self.seed)
+    }
+}
+
+/// A Random implementation in the style of the arcade TGM games, which avoids dealing any of
+/// the last 4 pieces
+///
+/// Each piece is rolled up to [rolls] times, and the roll is kept as soon as it isn't in the
+/// history of the last 4 pieces (or the rolls run out). The history starts as Z, S, S, Z, and
+/// the first piece is never an S, Z or O so the game never starts with an overhang.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HistoryRandom {
+    seed: u64,
+    rng: Pcg32,
+    rolls: u32,
+    history: [PieceType; 4],
+    first: bool,
+}
+
+impl HistoryRandom {
+    /// The number of rolls used by TGM2 and later, TGM1 used 4
+    pub const TGM_ROLLS: u32 = 6;
+
+    /// Creates a randomizer with a new random seed
+    pub fn new(rolls: u32) -> Self {
+        Self::with_seed(rolls, random_seed())
+    }
+
+    pub fn with_seed(rolls: u32, seed: u64) -> Self {
+        Self {
+            seed,
+            rng: Pcg32::new(seed),
+            rolls: rolls.max(1),
+            history: [PieceType::Z, PieceType::S, PieceType::S, PieceType::Z],
+            first: true,
+        }
+    }
+
+    fn roll(&mut self, piece_types: &[PieceType]) -> PieceType {
+        piece_types[self.rng.below(piece_types.len() as u32) as usize]
+    }
+}
+
+impl Random<PieceType> for HistoryRandom {
+    fn next(&mut self) -> PieceType {
+        let piece_type = if self.first {
+            self.first = false;
+            self.roll(&[PieceType::I, PieceType::J, PieceType::L, PieceType::T])
+        } else {
+            let mut piece_type = self.roll(&PieceType::all());
+            for _ in 1..self.rolls {
+                if !self.history.contains(&piece_type) {
+                    break;
+                }
+                piece_type = self.roll(&PieceType::all());
+            }
+            piece_type
+        };
+
+        self.history.rotate_left(1);
+        self.history[3] = piece_type;
+        piece_type
+    }
+
+    fn seed(&self) -> Option<u64> {
+        Some(self.seed)
+    }
+}
+
+/// A Random implementation that follows the NES game's generator
+///
+/// A roll picks one of 8 outcomes, where the 8th outcome or a repeat of the previous piece
+/// triggers a single reroll among the 7 pieces, which is kept whatever it is.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct NesRandom {
+    seed: u64,
+    rng: Pcg32,
+    previous: Option<PieceType>,
+}
+
+impl NesRandom {
+    /// The pieces in the order of the NES game's spawn table
+    const PIECE_TYPES: [PieceType; 7] = [
+        PieceType::T,
+        PieceType::J,
+        PieceType::Z,
+        PieceType::O,
+        PieceType::S,
+        PieceType::L,
+        PieceType::I,
+    ];
+
+    /// Creates a randomizer with a new random seed
+    pub fn new() -> Self {
+        Self::with_seed(random_seed())
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: Pcg32::new(seed),
+            previous: None,
+        }
+    }
+}
+
+impl Default for NesRandom {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Random<PieceType> for NesRandom {
+    fn next(&mut self) -> PieceType {
+        let index = self.rng.below(8) as usize;
+        let piece_type = match Self::PIECE_TYPES.get(index) {
+            Some(piece_type) if Some(*piece_type) != self.previous => *piece_type,
+            _ => Self::PIECE_TYPES[self.rng.below(7) as usize],
+        };
+        self.previous = Some(piece_type);
+        piece_type
+    }
+
+    fn seed(&self) -> Option<u64> {
+        Some(self.seed)
+    }
+}
+
+/// The piece randomizers that can be chosen for a game
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Randomizer {
+    /// Deals all 7 pieces in a random order before dealing them again
+    Bag,
+    /// Deals two of each piece in a random order before dealing them again
+    FourteenBag,
+    /// Every piece is equally likely every time
+    Pure,
+    /// Avoids the last 4 pieces dealt, like the arcade TGM games
+    History,
+    /// Rerolls repeated pieces once, like the NES game
+    Nes,
+}
+
+impl Randomizer {
+    pub fn all() -> Vec<Randomizer> {
+        vec![
+            Randomizer::Bag,
+            Randomizer::FourteenBag,
+            Randomizer::Pure,
+            Randomizer::History,
+            Randomizer::Nes,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Randomizer::Bag => "bag",
+            Randomizer::FourteenBag => "14-bag",
+            Randomizer::Pure => "pure",
+            Randomizer::History => "history",
+            Randomizer::Nes => "nes",
+        }
+    }
+
+    /// Creates a piece randomizer of this kind that deals pieces from the given seed
+    pub fn with_seed(&self, seed: u64) -> PieceRandom {
+        match self {
+            Randomizer::Bag => PieceRandom::Bag(RandomBag::with_seed(PieceType::all(), seed)),
+            Randomizer::FourteenBag => PieceRandom::Bag(RandomBag::with_seed(
+                [PieceType::all(), PieceType::all()].concat(),
+                seed,
+            )),
+            Randomizer::Pure => PieceRandom::Pure(PureRandom::with_seed(PieceType::all(), seed)),
+            Randomizer::History => {
+                PieceRandom::History(HistoryRandom::with_seed(HistoryRandom::TGM_ROLLS, seed))
+            }
+            Randomizer::Nes => PieceRandom::Nes(NesRandom::with_seed(seed)),
+        }
+    }
+}
+
+impl FromStr for Randomizer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Randomizer::all()
+            .into_iter()
+            .find(|randomizer| randomizer.name() == s)
+            .ok_or_else(|| format!("unknown randomizer '{}'", s))
+    }
+}
+
+/// A piece randomizer of any of the kinds in [Randomizer]
+#[derive(Clone, Serialize, Deserialize)]
+pub enum PieceRandom {
+    Bag(RandomBag<PieceType>),
+    Pure(PureRandom<PieceType>),
+    History(HistoryRandom),
+    Nes(NesRandom),
+    /// A fixed sequence of pieces, e.g. for a puzzle
+    Sequence(SequenceRandom<PieceType>),
+}
+
+impl Random<PieceType> for PieceRandom {
+    fn next(&mut self) -> PieceType {
+        match self {
+            PieceRandom::Bag(random) => random.next(),
+            PieceRandom::Pure(random) => random.next(),
+            PieceRandom::History(random) => random.next(),
+            PieceRandom::Nes(random) => random.next(),
+            PieceRandom::Sequence(random) => random.next(),
+        }
+    }
+
+    fn seed(&self) -> Option<u64> {
+        match self {
+            PieceRandom::Bag(random) => random.seed(),
+            PieceRandom::Pure(random) => random.seed(),
+            PieceRandom::History(random) => random.seed(),
+            PieceRandom::Nes(random) => random.seed(),
+            PieceRandom::Sequence(random) => random.seed(),
+        }
+    }
+}
+
+/// A Random implementation that returns a fixed sequence of values in order
+///
+/// Once the sequence is exhausted it keeps returning the last value, which is only there to fill
+/// the preview: games playing a sequence should stop dealing when it runs out, as puzzles and
+/// replays do with a piece limit.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SequenceRandom<T: Clone> {
+    values: Vec<T>,
+    index: usize,
+}
+
+impl<T: Clone> SequenceRandom<T> {
+    /// Panics if there are no values to return
+    pub fn new(values: Vec<T>) -> Self {
+        assert!(!values.is_empty(), "a sequence needs at least one value");
+        Self { values, index: 0 }
+    }
+}
+
+impl<T: Clone> Random<T> for SequenceRandom<T> {
+    fn next(&mut self) -> T {
+        let value = self.values[self.index.min(self.values.len() - 1)].clone();
+        self.index += 1;
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn take<T: Clone, TRandom: Random<T>>(random: &mut TRandom, count: usize) -> Vec<T> {
+        (0..count).map(|_| random.next()).collect()
+    }
+
+    #[test]
+    fn pcg32_matches_the_reference_implementation() {
+        // The first outputs of the reference pcg32 demo, seeded with 42 on stream 54
+        let mut rng = Pcg32::with_stream(42, 54);
+        let values: Vec<u32> = (0..6).map(|_| rng.next_u32()).collect();
+        assert_eq!(
+            values,
+            vec![0xa15c02b7, 0x7b47f409, 0xba1d3330, 0x83d2f293, 0xbfa4784b, 0xcbed606e]
+        );
+    }
+
+    #[test]
+    fn pcg32_below_stays_in_bounds() {
+        let mut rng = Pcg32::new(7);
+        assert!((0..1000).all(|_| rng.below(7) < 7));
+        assert!((0..1000).all(|_| rng.below(1) == 0));
+    }
+
+    #[test]
+    fn random_bag_is_deterministic_for_a_seed() {
+        let values: Vec<u32> = (0..7).collect();
+        let mut a = RandomBag::with_seed(values.to_vec(), 1234);
+        let mut b = RandomBag::with_seed(values.to_vec(), 1234);
+        let mut c = RandomBag::with_seed(values.to_vec(), 4321);
+
+        let sequence = take(&mut a, 70);
+        assert_eq!(sequence, take(&mut b, 70));
+        assert_ne!(sequence, take(&mut c, 70));
+        assert_eq!(a.seed(), Some(1234));
+    }
+
+    #[test]
+    fn random_bag_deals_every_value_once_per_bag() {
+        let mut bag = RandomBag::with_seed((0..7).collect(), 99);
+        for _ in 0..10 {
+            let mut values = take(&mut bag, 7);
+            values.sort();
+            assert_eq!(values, (0..7).collect::<Vec<u32>>());
+        }
+    }
+
+    #[test]
+    fn fourteen_bag_deals_every_piece_twice_per_bag() {
+        let mut bag = Randomizer::FourteenBag.with_seed(5);
+        for _ in 0..10 {
+            let piece_types = take(&mut bag, 14);
+            for piece_type in PieceType::all() {
+                assert_eq!(piece_types.iter().filter(|t| **t == piece_type).count(), 2);
+            }
+        }
+    }
+
+    #[test]
+    fn pure_random_deals_every_value() {
+        let mut random = PureRandom::with_seed(PieceType::all(), 3);
+        let piece_types = take(&mut random, 200);
+        assert!(PieceType::all()
+            .iter()
+            .all(|piece_type| piece_types.contains(piece_type)));
+    }
+
+    #[test]
+    fn history_random_never_starts_with_an_overhang() {
+        for seed in 0..100 {
+            let first = HistoryRandom::with_seed(HistoryRandom::TGM_ROLLS, seed).next();
+            assert!(![PieceType::S, PieceType::Z, PieceType::O].contains(&first));
+        }
+    }
+
+    #[test]
+    fn history_random_avoids_recent_pieces() {
+        // With enough rolls the history is always avoided, since 3 of the 7 pieces are free
+        let mut random = HistoryRandom::with_seed(1000, 11);
+        let piece_types = take(&mut random, 200);
+        for window in piece_types.windows(5) {
+            assert!(!window[..4].contains(&window[4]));
+        }
+    }
+
+    #[test]
+    fn nes_random_rarely_repeats() {
+        let mut random = NesRandom::with_seed(8);
+        let piece_types = take(&mut random, 7000);
+        let repeats = piece_types.windows(2).filter(|w| w[0] == w[1]).count();
+        // A repeat needs the reroll to land on the previous piece, so roughly 1 in 28 pieces
+        assert!(repeats > 100 && repeats < 400, "{} repeats", repeats);
+    }
+
+    #[test]
+    fn randomizers_are_deterministic_for_a_seed() {
+        for randomizer in Randomizer::all() {
+            let sequence = take(&mut randomizer.with_seed(77), 50);
+            assert_eq!(sequence, take(&mut randomizer.with_seed(77), 50));
+            assert_ne!(sequence, take(&mut randomizer.with_seed(78), 50));
+            assert_eq!(randomizer.with_seed(77).seed(), Some(77));
+            assert_eq!(randomizer.name().parse::<Randomizer>(), Ok(randomizer));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one value")]
+    fn bags_need_values_to_deal() {
+        RandomBag::<PieceType>::with_seed(vec![], 1);
+    }
+
+    #[test]
+    fn sequence_random_returns_its_values_in_order() {
+        let mut sequence = SequenceRandom::new(vec![1, 2, 3]);
+        assert_eq!(take(&mut sequence, 5), vec![1, 2, 3, 3, 3]);
+        assert_eq!(sequence.seed(), None);
+    }
 }
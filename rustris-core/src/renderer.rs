@@ -1,4 +1,9 @@
-use crate::game::Message;
+use std::fmt::Display;
+
+use crate::{
+    game::Message,
+    mode::{Outcome, Stats},
+};
 
 use super::{
     piece::{Piece, PieceSet, PieceType},
@@ -7,6 +12,31 @@ use super::{
 
 pub type BoardState = Vec<[bool; 10]>;
 
+/// A labelled statistic that a game mode wants shown while playing
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HudEntry {
+    pub label: String,
+    pub value: String,
+}
+
+impl HudEntry {
+    pub fn new(label: impl Into<String>, value: impl Display) -> Self {
+        Self {
+            label: label.into(),
+            value: value.to_string(),
+        }
+    }
+}
+
+/// Where a game with undo enabled is in its history of placements
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct HistoryPosition {
+    /// The number of placements that can be undone
+    pub placement: usize,
+    /// The number of placements in the history, including ones that can be redone
+    pub placements: usize,
+}
+
 pub struct RenderState<'a, TPieceSet: PieceSet> {
     pub board_state: BoardState,
     pub piece_set: &'a TPieceSet,
@@ -16,9 +46,21 @@ pub struct RenderState<'a, TPieceSet: PieceSet> {
     pub next_piece_types: Vec<PieceType>,
     pub paused: bool,
     pub messages: Vec<Message>,
+    /// The number of garbage lines waiting to be inserted
+    pub pending_garbage: u32,
+    /// The statistics the game mode wants shown
+    pub hud: Vec<HudEntry>,
+    pub stats: Stats,
+    /// How the game ended, or None while it's still being played
+    pub outcome: Option<Outcome>,
+    /// The game mode's banner for the outcome
+    pub outcome_text: Option<&'static str>,
+    /// Where the game is in its undo history, or None if undo is disabled
+    pub history: Option<HistoryPosition>,
 }
 
 impl<'a, TPieceSet: PieceSet> RenderState<'a, TPieceSet> {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         board_state: BoardState,
         piece_set: &'a TPieceSet,
@@ -28,6 +70,12 @@ impl<'a, TPieceSet: PieceSet> RenderState<'a, TPieceSet> {
         next_piece_types: Vec<PieceType>,
         paused: bool,
         messages: Vec<Message>,
+        pending_garbage: u32,
+        hud: Vec<HudEntry>,
+        stats: Stats,
+        outcome: Option<Outcome>,
+        outcome_text: Option<&'static str>,
+        history: Option<HistoryPosition>,
     ) -> Self {
         Self {
             board_state,
@@ -38,6 +86,12 @@ impl<'a, TPieceSet: PieceSet> RenderState<'a, TPieceSet> {
             next_piece_types,
             paused,
             messages,
+            pending_garbage,
+            hud,
+            stats,
+            outcome,
+            outcome_text,
+            history,
         }
     }
 }
@@ -46,3 +100,12 @@ pub trait Renderer<TPieceSet: PieceSet> {
     fn init(&mut self);
     fn render(&mut self, state: RenderState<TPieceSet>, delta_time: f64);
 }
+
+/// A renderer that draws nothing, for running games without a terminal
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NullRenderer;
+
+impl<TPieceSet: PieceSet> Renderer<TPieceSet> for NullRenderer {
+    fn init(&mut self) {}
+    fn render(&mut self, _: RenderState<TPieceSet>, _: f64) {}
+}
@@ -0,0 +1,464 @@
+use std::{
+    error::Error,
+    fmt,
+    io::{self, Read, Write},
+    sync::Arc,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    garbage::GarbageQueue,
+    input::{Action, Handling, InputActions},
+    mode::Mode,
+    piece::PieceType,
+    puzzle::Puzzle,
+    random::SequenceRandom,
+    rules::RuleSet,
+};
+
+/// The version written to new replay files
+///
+/// This must be increased whenever a change to [Replay] would stop older versions of rustris
+/// from reading the file correctly.
+pub const REPLAY_VERSION: u32 = 5;
+
+/// A recording of everything needed to reproduce a game exactly
+///
+/// Every dealt piece is stored alongside the seed, so a replay can be played back without
+/// knowing which randomizer dealt the pieces.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Replay {
+    pub version: u32,
+    /// The rules the game was played with
+    pub rules: RuleSet,
+    pub handling: Option<Handling>,
+    /// The seed of the randomizer that dealt the pieces, if it was seeded
+    pub seed: Option<u64>,
+    /// The seed of the game's garbage, which also places the garbage of modes like cheese
+    pub garbage_seed: u64,
+    /// The messiness of the incoming garbage queue
+    pub garbage_messiness: f64,
+    /// The built-in mode the game was played in, or None if it was played in a custom
+    /// [GameMode](crate::mode::GameMode), which the replay can't recreate
+    pub mode: Option<Mode>,
+    /// The puzzle the game was played from, which sets up its board and hold piece
+    pub puzzle: Option<Puzzle>,
+    /// The number of seconds simulated by each frame
+    pub frame_time: f64,
+    /// The total number of frames in the recording
+    pub frames: u64,
+    /// Every piece dealt by the randomizer, in order
+    pub pieces: Vec<PieceType>,
+    pub events: Vec<ReplayEvent>,
+}
+
+/// An action performed during a frame of a replay
+///
+/// Frame 0 is the first update of the game, so the time of the event is `frame * frame_time`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ReplayEvent {
+    pub frame: u64,
+    pub action: Action,
+}
+
+impl Replay {
+    /// Creates a randomizer that deals the recorded pieces in order
+    pub fn random(&self) -> SequenceRandom<PieceType> {
+        SequenceRandom::new(self.pieces.to_vec())
+    }
+
+    /// Creates the incoming garbage queue the game started with
+    pub fn garbage_queue(&self) -> GarbageQueue {
+        GarbageQueue::with_seed(self.garbage_messiness, self.garbage_seed)
+    }
+
+    /// Writes the replay in the versioned replay file format
+    pub fn save<W: Write>(&self, writer: W) -> Result<(), ReplayError> {
+        serde_json::to_writer(writer, self)?;
+        Ok(())
+    }
+
+    /// Reads a replay written by [save], rejecting versions this build doesn't understand and
+    /// replays without any pieces to deal
+    pub fn load<R: Read>(reader: R) -> Result<Self, ReplayError> {
+        let value: serde_json::Value = serde_json::from_reader(reader)?;
+        let version = value
+            .get("version")
+            .and_then(|version| version.as_u64())
+            .ok_or(ReplayError::MissingVersion)?;
+        if version != REPLAY_VERSION as u64 {
+            return Err(ReplayError::UnsupportedVersion(version));
+        }
+        let replay: Self = serde_json::from_value(value)?;
+        if replay.pieces.is_empty() {
+            return Err(ReplayError::NoPieces);
+        }
+        Ok(replay)
+    }
+}
+
+/// An error that occurred while reading or writing a replay
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    Format(serde_json::Error),
+    MissingVersion,
+    UnsupportedVersion(u64),
+    /// The replay has no pieces, so there's nothing to play back
+    NoPieces,
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(error) => write!(f, "could not access replay: {}", error),
+            ReplayError::Format(error) => write!(f, "invalid replay: {}", error),
+            ReplayError::MissingVersion => write!(f, "invalid replay: missing version"),
+            ReplayError::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported replay version {} (expected {})",
+                version, REPLAY_VERSION
+            ),
+            ReplayError::NoPieces => write!(f, "invalid replay: no pieces"),
+        }
+    }
+}
+
+impl Error for ReplayError {}
+
+impl From<io::Error> for ReplayError {
+    fn from(error: io::Error) -> Self {
+        ReplayError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for ReplayError {
+    fn from(error: serde_json::Error) -> Self {
+        if error.is_io() {
+            ReplayError::Io(error.into())
+        } else {
+            ReplayError::Format(error)
+        }
+    }
+}
+
+/// Records the frames and pieces of a game as it's played
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct ReplayRecorder {
+    mode: Option<Mode>,
+    puzzle: Option<Puzzle>,
+    frame_time: Option<f64>,
+    frames: u64,
+    pieces: Vec<PieceType>,
+    events: Vec<ReplayEvent>,
+}
+
+impl ReplayRecorder {
+    pub(crate) fn new() -> Self {
+        Self {
+            mode: Some(Mode::Endless),
+            puzzle: None,
+            frame_time: None,
+            frames: 0,
+            pieces: vec![],
+            events: vec![],
+        }
+    }
+
+    /// Records the mode the game is played in, or None for a custom mode
+    pub(crate) fn set_mode(&mut self, mode: Option<Mode>) {
+        self.mode = mode;
+        self.puzzle = None;
+    }
+
+    pub(crate) fn set_puzzle(&mut self, puzzle: &Puzzle) {
+        self.mode = Some(Mode::Puzzle { goal: puzzle.goal });
+        self.puzzle = Some(puzzle.clone());
+    }
+
+    pub(crate) fn record_frame(&mut self, delta_time: f64, actions: &[Action]) {
+        self.frame_time.get_or_insert(delta_time);
+        for action in actions {
+            self.events.push(ReplayEvent {
+                frame: self.frames,
+                action: *action,
+            });
+        }
+        self.frames += 1;
+    }
+
+    /// Records garbage received before the next frame, which is played back at the start of it
+    pub(crate) fn record_garbage(&mut self, lines: u32) {
+        self.events.push(ReplayEvent {
+            frame: self.frames,
+            action: Action::ReceiveGarbage(lines),
+        });
+    }
+
+    /// The number of frames recorded so far
+    pub(crate) fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Drops everything recorded from the given frame on, so recording carries on from there
+    pub(crate) fn rewind(&mut self, frames: u64) {
+        let events = self.events.partition_point(|event| event.frame < frames);
+        self.events.truncate(events);
+        self.frames = frames;
+    }
+
+    /// Records the piece dealt as the [index]th piece of the game, unless it was already dealt
+    /// before an undo took it back
+    pub(crate) fn record_piece(&mut self, index: usize, piece_type: PieceType) {
+        if index == self.pieces.len() {
+            self.pieces.push(piece_type);
+        }
+    }
+
+    /// Creates a replay of everything recorded so far
+    ///
+    /// [pieces_dealt] is the number of pieces played so far, which can be fewer than were
+    /// recorded after an undo, [next_piece_types] are the pieces that have been dealt into the
+    /// queue but not played yet, and [garbage] is the game's incoming garbage queue.
+    pub(crate) fn replay(
+        &self,
+        rules: RuleSet,
+        handling: Option<Handling>,
+        seed: Option<u64>,
+        pieces_dealt: usize,
+        next_piece_types: &[PieceType],
+        garbage: &GarbageQueue,
+    ) -> Replay {
+        let mut pieces = self.pieces[..pieces_dealt.min(self.pieces.len())].to_vec();
+        pieces.extend_from_slice(next_piece_types);
+        Replay {
+            version: REPLAY_VERSION,
+            rules,
+            handling,
+            seed,
+            garbage_seed: garbage.seed(),
+            garbage_messiness: garbage.messiness(),
+            mode: self.mode,
+            puzzle: self.puzzle.clone(),
+            frame_time: self.frame_time.unwrap_or_default(),
+            frames: self.frames,
+            pieces,
+            events: self.events.to_vec(),
+        }
+    }
+}
+
+/// An implementation of InputActions that plays back the actions of a replay
+///
+/// Every call to [actions] plays one frame of the replay, so the game should be updated with
+/// the replay's frame time to reproduce it exactly. The events are shared between clones, so a
+/// clone only copies how far through the replay it is.
+#[derive(Clone)]
+pub struct ReplayInputActions {
+    events: Arc<[ReplayEvent]>,
+    frames: u64,
+    handling: Option<Handling>,
+    frame: u64,
+    next_event: usize,
+}
+
+impl ReplayInputActions {
+    pub fn new(replay: &Replay) -> Self {
+        Self {
+            events: replay.events.as_slice().into(),
+            frames: replay.frames,
+            handling: replay.handling,
+            frame: 0,
+            next_event: 0,
+        }
+    }
+
+    /// The number of frames that have been played so far
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// The total number of frames in the replay
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Returns true once every frame of the replay has been played
+    pub fn is_finished(&self) -> bool {
+        self.frame >= self.frames
+    }
+}
+
+impl InputActions for ReplayInputActions {
+    fn actions(&mut self, _: f64) -> Vec<Action> {
+        let mut actions = Vec::<Action>::new();
+        while let Some(event) = self.events.get(self.next_event) {
+            if event.frame > self.frame {
+                break;
+            }
+            actions.push(event.action);
+            self.next_event += 1;
+        }
+        self.frame += 1;
+        actions
+    }
+
+    fn handling(&self) -> Option<Handling> {
+        self.handling
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replay() -> Replay {
+        let mut recorder = ReplayRecorder::new();
+        recorder.record_piece(0, PieceType::T);
+        recorder.record_frame(0.5, &[Action::MoveLeft, Action::RotateRight]);
+        recorder.record_frame(0.5, &[]);
+        recorder.record_frame(0.5, &[Action::HardDrop]);
+        recorder.record_piece(1, PieceType::I);
+        recorder.replay(
+            RuleSet::classic(),
+            Some(Handling {
+                delayed_auto_shift: 0.25,
+                auto_repeat_rate: 0f64,
+            }),
+            Some(42),
+            2,
+            &[PieceType::O],
+            &GarbageQueue::with_seed(0.5, 7),
+        )
+    }
+
+    #[test]
+    fn recorder_keeps_pieces_dealt_again_after_an_undo_once() {
+        let mut recorder = ReplayRecorder::new();
+        recorder.record_piece(0, PieceType::T);
+        recorder.record_piece(1, PieceType::I);
+        recorder.record_piece(1, PieceType::I);
+        let replay = recorder.replay(
+            RuleSet::guideline(),
+            None,
+            None,
+            1,
+            &[PieceType::I],
+            &GarbageQueue::new(0f64),
+        );
+        assert_eq!(replay.pieces, vec![PieceType::T, PieceType::I]);
+    }
+
+    #[test]
+    fn recorder_captures_frames_and_pieces() {
+        let replay = replay();
+        assert_eq!(replay.frame_time, 0.5);
+        assert_eq!(replay.frames, 3);
+        assert_eq!(
+            replay.pieces,
+            vec![PieceType::T, PieceType::I, PieceType::O]
+        );
+        assert_eq!(
+            replay.events,
+            vec![
+                ReplayEvent {
+                    frame: 0,
+                    action: Action::MoveLeft
+                },
+                ReplayEvent {
+                    frame: 0,
+                    action: Action::RotateRight
+                },
+                ReplayEvent {
+                    frame: 2,
+                    action: Action::HardDrop
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn rewinding_drops_later_frames() {
+        let mut recorder = ReplayRecorder::new();
+        recorder.record_frame(0.5, &[Action::MoveLeft]);
+        recorder.record_frame(0.5, &[Action::HardDrop]);
+        recorder.rewind(1);
+        recorder.record_frame(0.5, &[Action::Hold]);
+        let replay = recorder.replay(
+            RuleSet::guideline(),
+            None,
+            None,
+            0,
+            &[],
+            &GarbageQueue::new(0f64),
+        );
+        assert_eq!(replay.frames, 2);
+        assert_eq!(
+            replay.events,
+            vec![
+                ReplayEvent {
+                    frame: 0,
+                    action: Action::MoveLeft
+                },
+                ReplayEvent {
+                    frame: 1,
+                    action: Action::Hold
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn input_actions_play_back_each_frame() {
+        let mut input_actions = ReplayInputActions::new(&replay());
+        assert_eq!(
+            input_actions.actions(0.5),
+            vec![Action::MoveLeft, Action::RotateRight]
+        );
+        assert_eq!(input_actions.actions(0.5), vec![]);
+        assert!(!input_actions.is_finished());
+        assert_eq!(input_actions.actions(0.5), vec![Action::HardDrop]);
+        assert!(input_actions.is_finished());
+        assert_eq!(input_actions.handling(), replay().handling);
+    }
+
+    #[test]
+    fn save_and_load() {
+        let mut file = Vec::new();
+        replay().save(&mut file).unwrap();
+        assert_eq!(Replay::load(file.as_slice()).unwrap(), replay());
+    }
+
+    #[test]
+    fn load_rejects_other_versions() {
+        let mut replay = replay();
+        replay.version = REPLAY_VERSION + 1;
+        let mut file = Vec::new();
+        replay.save(&mut file).unwrap();
+        assert!(matches!(
+            Replay::load(file.as_slice()),
+            Err(ReplayError::UnsupportedVersion(version)) if version == REPLAY_VERSION as u64 + 1
+        ));
+        assert!(matches!(
+            Replay::load("{}".as_bytes()),
+            Err(ReplayError::MissingVersion)
+        ));
+        assert!(matches!(
+            Replay::load("replay".as_bytes()),
+            Err(ReplayError::Format(_))
+        ));
+    }
+
+    #[test]
+    fn load_rejects_replays_without_pieces() {
+        let mut replay = replay();
+        replay.pieces.clear();
+        let mut file = Vec::new();
+        replay.save(&mut file).unwrap();
+        assert!(matches!(
+            Replay::load(file.as_slice()),
+            Err(ReplayError::NoPieces)
+        ));
+    }
+}
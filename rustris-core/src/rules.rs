@@ -0,0 +1,392 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    attack::ClearInfo,
+    input::Handling,
+    position::Position,
+    score::{arcade_clear_score, classic_clear_score, clear_score},
+};
+
+/// Gravity of 20 lines per frame at 60 frames per second, which drops pieces straight onto the
+/// stack as soon as they spawn
+pub const TWENTY_G: f64 = 20f64 * 60f64;
+
+/// How fast pieces fall on each level
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum GravityCurve {
+    /// The same number of lines per second on every level
+    Fixed(f64),
+    /// The guideline curve, which starts at a line per second and reaches 20G after level 19
+    Guideline,
+    /// The frames per line table of the classic console game at 60 frames per second, where
+    /// level 1 here is level 0 there
+    Classic,
+    /// The internal gravity table of the arcade games, which counts levels from 0 to 999 and
+    /// reaches 20G at level 500
+    Arcade,
+}
+
+impl GravityCurve {
+    /// Frames per line on each classic level, with every level from the classic level 29 kill
+    /// screen onwards at 1 frame
+    const CLASSIC_FRAMES: [u32; 29] = [
+        48, 43, 38, 33, 28, 23, 18, 13, 8, 6, 5, 5, 5, 4, 4, 4, 3, 3, 3, 2, 2, 2, 2, 2, 2, 2, 2, 2,
+        2,
+    ];
+
+    /// The gravity from each level of the arcade table onwards, in 1/256ths of a line per frame
+    const ARCADE_GRAVITY: [(u32, u32); 30] = [
+        (0, 4),
+        (30, 6),
+        (35, 8),
+        (40, 10),
+        (50, 12),
+        (60, 16),
+        (70, 32),
+        (80, 48),
+        (90, 64),
+        (100, 80),
+        (120, 96),
+        (140, 112),
+        (160, 128),
+        (170, 144),
+        (200, 4),
+        (220, 32),
+        (230, 64),
+        (233, 96),
+        (236, 128),
+        (239, 160),
+        (243, 192),
+        (247, 224),
+        (251, 256),
+        (300, 512),
+        (330, 768),
+        (360, 1024),
+        (400, 1280),
+        (420, 1024),
+        (450, 768),
+        (500, 5120),
+    ];
+
+    /// The lines per second pieces fall at on a level, counting levels from 1 apart from the
+    /// arcade curve
+    pub fn lines_per_second(&self, level: u32) -> f64 {
+        match self {
+            GravityCurve::Fixed(lines_per_second) => *lines_per_second,
+            GravityCurve::Guideline => {
+                // The formula has reached 20G by level 20, and goes wrong past level 115 where
+                // its base turns negative
+                let level = level.clamp(1, 20) as f64;
+                let seconds_per_line = (0.8 - (level - 1f64) * 0.007).powf(level - 1f64);
+                (1f64 / seconds_per_line).min(TWENTY_G)
+            }
+            GravityCurve::Classic => {
+                let frames = Self::CLASSIC_FRAMES
+                    .get(level.max(1) as usize - 1)
+                    .copied()
+                    .unwrap_or(1);
+                60f64 / frames as f64
+            }
+            GravityCurve::Arcade => {
+                let gravity = Self::ARCADE_GRAVITY
+                    .iter()
+                    .rev()
+                    .find(|(start, _)| level >= *start)
+                    .map_or(4, |(_, gravity)| *gravity);
+                gravity as f64 / 256f64 * 60f64
+            }
+        }
+    }
+}
+
+/// When a piece resting on the stack locks in place
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum LockDelay {
+    /// Pieces only lock when they're hard dropped
+    Infinite,
+    /// Pieces lock once they've rested on the stack for the given number of seconds
+    ///
+    /// Moving or rotating the piece restarts the timer up to [resets] times, and dropping to a
+    /// new lowest row restarts it and gives back every reset.
+    Timed { seconds: f64, resets: u32 },
+    /// Pieces lock as soon as gravity tries to move them down and they can't fall, like in the
+    /// classic games
+    Gravity,
+    /// Pieces lock after 30 frames on the stack, or 17 frames from level 900, and only dropping
+    /// to a new lowest row restarts the timer, like the arcade games
+    Arcade,
+}
+
+impl LockDelay {
+    /// The number of seconds a piece can rest on the stack on a level and the number of move
+    /// resets it gets, or None if pieces don't lock on a timer
+    pub fn timer(&self, level: u32) -> Option<(f64, u32)> {
+        match self {
+            LockDelay::Infinite | LockDelay::Gravity => None,
+            LockDelay::Timed { seconds, resets } => Some((*seconds, *resets)),
+            LockDelay::Arcade if level < 900 => Some((30f64 / 60f64, 0)),
+            LockDelay::Arcade => Some((17f64 / 60f64, 0)),
+        }
+    }
+}
+
+/// How long the next piece waits to spawn after a piece locks
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum EntryDelay {
+    /// The same number of seconds after every piece
+    Fixed(f64),
+    /// 10 frames after pieces that lock in the bottom two rows, and 2 frames longer for every 4
+    /// rows higher up to 18 frames, like the classic console game
+    ByLockHeight,
+    /// 25 frames, shrinking to 16 frames from level 700 and 12 frames from level 800, like the
+    /// arcade games
+    Arcade,
+}
+
+impl EntryDelay {
+    /// The delay after a piece whose lowest unit locked in the given row, on a level
+    pub fn seconds(&self, lock_row: usize, level: u32) -> f64 {
+        let frames = match self {
+            EntryDelay::Fixed(seconds) => return *seconds,
+            EntryDelay::ByLockHeight => (10 + 2 * ((lock_row + 2) / 4)).min(18),
+            EntryDelay::Arcade if level < 700 => 25,
+            EntryDelay::Arcade if level < 800 => 16,
+            EntryDelay::Arcade => 12,
+        };
+        frames as f64 / 60f64
+    }
+}
+
+/// How clears are scored
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Scoring {
+    /// Guideline scoring, with bonuses for twists, combos, back-to-back clears and all clears
+    Guideline,
+    /// Classic scoring, where only the number of lines counts
+    Classic,
+    /// Arcade scoring, where clears are worth more on higher levels and in combos
+    Arcade,
+}
+
+impl Scoring {
+    pub fn clear_score(&self, clear: &ClearInfo, level: u32) -> u32 {
+        match self {
+            Scoring::Guideline => clear_score(clear, level),
+            Scoring::Classic => classic_clear_score(clear, level),
+            Scoring::Arcade => arcade_clear_score(clear, level),
+        }
+    }
+}
+
+/// Whether pieces locking above the visible playfield end the game
+///
+/// The game always ends when a new piece has no room to spawn, whatever this is set to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum LockOut {
+    /// Pieces can lock anywhere on the board
+    Never,
+    /// The game ends when a piece locks entirely above the visible playfield
+    Entirely,
+    /// The game ends when any part of a piece locks above the visible playfield
+    Partially,
+}
+
+/// Every rule that decides what game is being played, apart from the piece set, randomizer and
+/// mode
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RuleSet {
+    /// The number of upcoming pieces shown in the next queue
+    pub preview_count: usize,
+    pub hold_enabled: bool,
+    pub ghost_enabled: bool,
+    pub hard_drop_enabled: bool,
+    pub lock_delay: LockDelay,
+    /// The delay between a piece locking and the next piece spawning (ARE)
+    pub are: EntryDelay,
+    /// Whether rotating while waiting for the next piece to spawn rotates it as it spawns (IRS)
+    pub initial_rotation: bool,
+    pub gravity: GravityCurve,
+    /// The lines per second pieces fall while soft dropping, unless gravity is already faster
+    pub soft_drop_speed: f64,
+    /// Where new pieces spawn, in the upright rotation
+    pub spawn_position: Position,
+    pub lock_out: LockOut,
+    pub scoring: Scoring,
+    /// The handling every player has to use, or None to let players choose their own
+    pub handling: Option<Handling>,
+    /// The number of placements that can be taken back with undo, for practice, or 0 to disable
+    /// undo as every preset does
+    ///
+    /// Games also have to turn undo on with [enable_undo](crate::game::Rustris::enable_undo).
+    pub undo_limit: usize,
+    /// Whether a piece placed with more key presses than it needed goes back to spawn to be
+    /// placed again, for practicing finesse
+    pub finesse_training: bool,
+}
+
+impl RuleSet {
+    /// The rules of modern guideline games, with a 5 piece preview, hold, hard drop and a half
+    /// second lock delay that can be reset 15 times
+    pub fn guideline() -> Self {
+        Self {
+            preview_count: 5,
+            hold_enabled: true,
+            ghost_enabled: true,
+            hard_drop_enabled: true,
+            lock_delay: LockDelay::Timed {
+                seconds: 0.5,
+                resets: 15,
+            },
+            are: EntryDelay::Fixed(0f64),
+            initial_rotation: false,
+            gravity: GravityCurve::Guideline,
+            soft_drop_speed: 50f64,
+            spawn_position: Position::new(4, 19),
+            lock_out: LockOut::Entirely,
+            scoring: Scoring::Guideline,
+            handling: None,
+            undo_limit: 0,
+            finesse_training: false,
+        }
+    }
+
+    /// The rules of the classic NES game, with a single preview piece, no hold, ghost or hard
+    /// drop, pieces locking as soon as they land, and the fixed 16 frame DAS and 6 frame ARR
+    ///
+    /// Levels start at 1 where the NES starts at level 0, so the gravity table and scoring are
+    /// shifted by one to match.
+    pub fn classic() -> Self {
+        Self {
+            preview_count: 1,
+            hold_enabled: false,
+            ghost_enabled: false,
+            hard_drop_enabled: false,
+            lock_delay: LockDelay::Gravity,
+            are: EntryDelay::ByLockHeight,
+            initial_rotation: false,
+            gravity: GravityCurve::Classic,
+            soft_drop_speed: 30f64,
+            spawn_position: Position::new(4, 19),
+            lock_out: LockOut::Never,
+            scoring: Scoring::Classic,
+            handling: Some(Handling {
+                delayed_auto_shift: 16f64 / 60f64,
+                auto_repeat_rate: 6f64 / 60f64,
+            }),
+            undo_limit: 0,
+            finesse_training: false,
+        }
+    }
+
+    /// The rules of the arcade games, where gravity climbs to 20G by level 500 and the lock
+    /// delay and entry delay give the time to place pieces, rotating them as they spawn
+    ///
+    /// These are meant to be played with the arcade mode, which counts levels from 0 to 999.
+    pub fn arcade() -> Self {
+        Self {
+            preview_count: 1,
+            hold_enabled: false,
+            ghost_enabled: false,
+            hard_drop_enabled: true,
+            lock_delay: LockDelay::Arcade,
+            are: EntryDelay::Arcade,
+            initial_rotation: true,
+            gravity: GravityCurve::Arcade,
+            soft_drop_speed: 60f64,
+            spawn_position: Position::new(4, 19),
+            lock_out: LockOut::Never,
+            scoring: Scoring::Arcade,
+            handling: None,
+            undo_limit: 0,
+            finesse_training: false,
+        }
+    }
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        Self::guideline()
+    }
+}
+
+impl FromStr for RuleSet {
+    type Err = String;
+
+    /// Parses one of the preset rule sets by name
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "guideline" => Ok(Self::guideline()),
+            "classic" => Ok(Self::classic()),
+            "arcade" => Ok(Self::arcade()),
+            _ => Err(format!("unknown rules '{}'", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guideline_gravity_speeds_up() {
+        assert_eq!(GravityCurve::Guideline.lines_per_second(1), 1f64);
+        assert!((GravityCurve::Guideline.lines_per_second(2) - 1.0 / 0.793).abs() < 1e-9);
+        assert!(GravityCurve::Guideline.lines_per_second(15) > 60f64);
+        assert_eq!(GravityCurve::Guideline.lines_per_second(30), TWENTY_G);
+        assert_eq!(GravityCurve::Guideline.lines_per_second(116), TWENTY_G);
+        assert_eq!(GravityCurve::Guideline.lines_per_second(300), TWENTY_G);
+    }
+
+    #[test]
+    fn classic_gravity_follows_the_frame_table() {
+        assert_eq!(GravityCurve::Classic.lines_per_second(1), 60f64 / 48f64);
+        assert_eq!(GravityCurve::Classic.lines_per_second(10), 10f64);
+        assert_eq!(GravityCurve::Classic.lines_per_second(29), 30f64);
+        assert_eq!(GravityCurve::Classic.lines_per_second(30), 60f64);
+        assert_eq!(GravityCurve::Fixed(3f64).lines_per_second(12), 3f64);
+    }
+
+    #[test]
+    fn classic_entry_delay_grows_with_lock_height() {
+        let frames = |lock_row| EntryDelay::ByLockHeight.seconds(lock_row, 1) * 60f64;
+        assert_eq!(frames(0), 10f64);
+        assert_eq!(frames(1), 10f64);
+        assert_eq!(frames(2), 12f64);
+        assert_eq!(frames(5), 12f64);
+        assert_eq!(frames(6), 14f64);
+        assert_eq!(frames(14), 18f64);
+        assert_eq!(frames(19), 18f64);
+    }
+
+    #[test]
+    fn arcade_gravity_reaches_twenty_g() {
+        let gravity = |level| GravityCurve::Arcade.lines_per_second(level) * 256f64 / 60f64;
+        assert_eq!(gravity(0), 4f64);
+        assert_eq!(gravity(35), 8f64);
+        assert_eq!(gravity(199), 144f64);
+        assert_eq!(gravity(200), 4f64);
+        assert_eq!(gravity(251), 256f64);
+        assert_eq!(GravityCurve::Arcade.lines_per_second(500), TWENTY_G);
+        assert_eq!(GravityCurve::Arcade.lines_per_second(999), TWENTY_G);
+    }
+
+    #[test]
+    fn arcade_delays_shrink_at_high_levels() {
+        let are = |level| EntryDelay::Arcade.seconds(0, level) * 60f64;
+        assert_eq!(are(0), 25f64);
+        assert_eq!(are(700), 16f64);
+        assert_eq!(are(999), 12f64);
+        assert_eq!(LockDelay::Arcade.timer(899), Some((0.5, 0)));
+        assert_eq!(LockDelay::Arcade.timer(900), Some((17f64 / 60f64, 0)));
+        assert_eq!(LockDelay::Gravity.timer(1), None);
+    }
+
+    #[test]
+    fn parses_presets() {
+        assert_eq!("classic".parse(), Ok(RuleSet::classic()));
+        assert_eq!("guideline".parse(), Ok(RuleSet::default()));
+        assert!("tgm".parse::<RuleSet>().is_err());
+    }
+}
@@ -0,0 +1,141 @@
+use crate::{attack::ClearInfo, twist::TwistKind};
+
+/// Points for each line a piece is soft dropped
+pub const SOFT_DROP_POINTS: u32 = 1;
+/// Points for each line a piece is hard dropped
+pub const HARD_DROP_POINTS: u32 = 2;
+
+/// The points scored by locking a piece, following the guideline scoring rules
+///
+/// Difficult clears are worth 50% more when back-to-back, each clear in a combo adds 50 points
+/// per combo, and all clears add a bonus on top. Everything is multiplied by the level.
+pub fn clear_score(clear: &ClearInfo, level: u32) -> u32 {
+    let mut score = match (clear.twist, clear.lines) {
+        (TwistKind::None, 0) => 0,
+        (TwistKind::None, 1) => 100,
+        (TwistKind::None, 2) => 300,
+        (TwistKind::None, 3) => 500,
+        (TwistKind::None, _) => 800,
+        (TwistKind::Mini, 0) => 100,
+        (TwistKind::Mini, 1) => 200,
+        (TwistKind::Mini, _) => 400,
+        (TwistKind::Full, 0) => 400,
+        (TwistKind::Full, 1) => 800,
+        (TwistKind::Full, 2) => 1200,
+        (TwistKind::Full, _) => 1600,
+    };
+    if clear.back_to_back {
+        score += score / 2;
+    }
+    if clear.lines > 0 {
+        score += 50 * clear.combo;
+    }
+    if clear.all_clear {
+        score += match clear.lines {
+            1 => 800,
+            2 => 1200,
+            3 => 1800,
+            _ if clear.back_to_back => 3200,
+            _ => 2000,
+        };
+    }
+    score * level
+}
+
+/// The points scored by a clear in the classic console games, where only the number of lines
+/// counts
+///
+/// The classic games multiply by their level plus one, which is the level here since classic
+/// levels start at 0.
+pub fn classic_clear_score(clear: &ClearInfo, level: u32) -> u32 {
+    let score = match clear.lines {
+        0 => 0,
+        1 => 40,
+        2 => 100,
+        3 => 300,
+        _ => 1200,
+    };
+    score * level
+}
+
+/// The points scored by a clear in the arcade games, which grow with the level
+///
+/// Each clear before it in a combo multiplies the score by one more, and all clears multiply it
+/// by 4.
+pub fn arcade_clear_score(clear: &ClearInfo, level: u32) -> u32 {
+    if clear.lines == 0 {
+        return 0;
+    }
+    let bravo = if clear.all_clear { 4 } else { 1 };
+    (level + clear.lines).div_ceil(4) * clear.lines * (1 + clear.combo) * bravo
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clear(lines: u32, twist: TwistKind) -> ClearInfo {
+        ClearInfo {
+            lines,
+            twist,
+            combo: 0,
+            back_to_back: false,
+            all_clear: false,
+        }
+    }
+
+    #[test]
+    fn scores_clears() {
+        assert_eq!(clear_score(&clear(0, TwistKind::None), 1), 0);
+        assert_eq!(clear_score(&clear(4, TwistKind::None), 1), 800);
+        assert_eq!(clear_score(&clear(2, TwistKind::Full), 1), 1200);
+        assert_eq!(clear_score(&clear(0, TwistKind::Mini), 1), 100);
+        assert_eq!(clear_score(&clear(3, TwistKind::None), 3), 1500);
+    }
+
+    #[test]
+    fn scores_bonuses() {
+        let back_to_back = ClearInfo {
+            back_to_back: true,
+            ..clear(4, TwistKind::None)
+        };
+        assert_eq!(clear_score(&back_to_back, 1), 1200);
+
+        let combo = ClearInfo {
+            combo: 3,
+            ..clear(1, TwistKind::None)
+        };
+        assert_eq!(clear_score(&combo, 2), 500);
+
+        let all_clear = ClearInfo {
+            all_clear: true,
+            ..clear(2, TwistKind::None)
+        };
+        assert_eq!(clear_score(&all_clear, 1), 1500);
+    }
+
+    #[test]
+    fn arcade_scores_grow_with_the_level() {
+        assert_eq!(arcade_clear_score(&clear(0, TwistKind::None), 500), 0);
+        assert_eq!(arcade_clear_score(&clear(1, TwistKind::None), 0), 1);
+        assert_eq!(arcade_clear_score(&clear(4, TwistKind::None), 100), 104);
+        let combo_all_clear = ClearInfo {
+            combo: 1,
+            all_clear: true,
+            ..clear(2, TwistKind::None)
+        };
+        assert_eq!(arcade_clear_score(&combo_all_clear, 10), 48);
+    }
+
+    #[test]
+    fn classic_scores_ignore_bonuses() {
+        assert_eq!(classic_clear_score(&clear(1, TwistKind::None), 1), 40);
+        assert_eq!(classic_clear_score(&clear(4, TwistKind::None), 19), 22800);
+        let twist_double = ClearInfo {
+            combo: 2,
+            back_to_back: true,
+            ..clear(2, TwistKind::Full)
+        };
+        assert_eq!(classic_clear_score(&twist_double, 2), 200);
+    }
+}
@@ -0,0 +1,137 @@
+use std::{
+    error::Error,
+    fmt,
+    io::{self, Read, Write},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    board::Board,
+    game::{AttackFeature, FinesseFeature, GravityFeature, HoldFeature, LockFeature},
+    garbage::GarbageQueue,
+    mode::{Outcome, Stats},
+    piece::{Direction, Piece, PieceType},
+    queue::Queue,
+    random::Random,
+    replay::ReplayRecorder,
+    rules::RuleSet,
+};
+
+/// The version written to new snapshot files
+///
+/// This must be increased whenever a change to [GameSnapshot] would stop older versions of
+/// rustris from reading the file correctly.
+pub const SNAPSHOT_VERSION: u32 = 2;
+
+/// The whole simulation state of a game, which can be saved to suspend the game and loaded to
+/// resume it exactly where it left off
+///
+/// Unlike a [Keyframe](crate::game::Keyframe), a snapshot doesn't include the game's input
+/// actions, renderer or attack table, which are set up again when the game is created. The mode
+/// is saved with [save_state](crate::mode::GameMode::save_state), so a snapshot can only be
+/// restored onto a game playing the same mode.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GameSnapshot<TRandom: Random<PieceType>> {
+    pub version: u32,
+    /// The rules the game is played with
+    pub rules: RuleSet,
+    /// The name of the game's mode
+    pub mode: String,
+    pub(crate) mode_state: Value,
+    pub(crate) board: Board,
+    pub(crate) active_piece: Option<Piece>,
+    /// The queue's randomizer, including the state of its random number generator
+    pub(crate) queue: Queue<PieceType, TRandom>,
+    pub(crate) hold_feature: HoldFeature,
+    pub(crate) gravity_feature: GravityFeature,
+    pub(crate) lock_feature: LockFeature,
+    pub(crate) spawn_timer: Option<f64>,
+    pub(crate) initial_rotation: Option<Direction>,
+    pub(crate) garbage: GarbageQueue,
+    pub(crate) outgoing_garbage: u32,
+    pub(crate) garbage_rows: u32,
+    pub(crate) attack_feature: AttackFeature,
+    pub(crate) finesse_feature: FinesseFeature,
+    /// Everything played so far, so the replay of a resumed game covers the whole game
+    pub(crate) replay_recorder: ReplayRecorder,
+    pub(crate) paused: bool,
+    pub(crate) piece_limit: Option<u32>,
+    pub(crate) pieces_dealt: u32,
+    pub(crate) stats: Stats,
+    pub(crate) outcome: Option<Outcome>,
+}
+
+impl<TRandom: Random<PieceType> + Serialize + for<'de> Deserialize<'de>> GameSnapshot<TRandom> {
+    /// Writes the snapshot in the versioned snapshot file format
+    pub fn save<W: Write>(&self, writer: W) -> Result<(), SnapshotError> {
+        serde_json::to_writer(writer, self)?;
+        Ok(())
+    }
+
+    /// Reads a snapshot written by [save], rejecting versions this build doesn't understand
+    pub fn load<R: Read>(reader: R) -> Result<Self, SnapshotError> {
+        let value: Value = serde_json::from_reader(reader)?;
+        let version = value
+            .get("version")
+            .and_then(|version| version.as_u64())
+            .ok_or(SnapshotError::MissingVersion)?;
+        if version != SNAPSHOT_VERSION as u64 {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+/// An error that occurred while reading, writing or restoring a snapshot
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    Format(serde_json::Error),
+    MissingVersion,
+    UnsupportedVersion(u64),
+    /// The snapshot was taken from a game playing a different mode to the one restoring it
+    ModeMismatch {
+        snapshot: String,
+        game: String,
+    },
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Io(error) => write!(f, "could not access snapshot: {}", error),
+            SnapshotError::Format(error) => write!(f, "invalid snapshot: {}", error),
+            SnapshotError::MissingVersion => write!(f, "invalid snapshot: missing version"),
+            SnapshotError::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported snapshot version {} (expected {})",
+                version, SNAPSHOT_VERSION
+            ),
+            SnapshotError::ModeMismatch { snapshot, game } => write!(
+                f,
+                "snapshot of a {} game can't be restored into a {} game",
+                snapshot, game
+            ),
+        }
+    }
+}
+
+impl Error for SnapshotError {}
+
+impl From<io::Error> for SnapshotError {
+    fn from(error: io::Error) -> Self {
+        SnapshotError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for SnapshotError {
+    fn from(error: serde_json::Error) -> Self {
+        if error.is_io() {
+            SnapshotError::Io(error.into())
+        } else {
+            SnapshotError::Format(error)
+        }
+    }
+}
@@ -1,11 +1,30 @@
 use crate::{
     board::Board,
-    piece::{Piece, PieceSet, PieceType},
+    piece::{Piece, PieceSet, PieceType, Rotation},
     position::Position,
 };
 
+/// How a locked piece was twisted into place
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TwistKind {
+    None,
+    /// A twist that only partly meets the detector's rules, which is usually worth less
+    Mini,
+    Full,
+}
+
 pub trait TwistDetector<TPieceSet: PieceSet> {
     fn is_twist(&self, board: &Board, piece_set: &TPieceSet, piece: &Piece) -> bool;
+
+    /// Distinguishes mini twists from full twists
+    /// Detectors without a notion of mini twists only report full twists.
+    fn twist_kind(&self, board: &Board, piece_set: &TPieceSet, piece: &Piece) -> TwistKind {
+        if self.is_twist(board, piece_set, piece) {
+            TwistKind::Full
+        } else {
+            TwistKind::None
+        }
+    }
 }
 
 pub struct ThreeCornerTTwistDetector;
@@ -33,7 +52,29 @@ impl<TPieceSet: PieceSet> TwistDetector<TPieceSet> for ThreeCornerTTwistDetector
             return corner_count >= 3;
         }
 
-        return false;
+        false
+    }
+
+    /// A twist is only full if both corners on the side the T is pointing are filled
+    fn twist_kind(&self, board: &Board, piece_set: &TPieceSet, piece: &Piece) -> TwistKind {
+        if !self.is_twist(board, piece_set, piece) {
+            return TwistKind::None;
+        }
+
+        let front_corners = match piece.rotation {
+            Rotation::Up => [Position::new(-1, 1), Position::new(1, 1)],
+            Rotation::Right => [Position::new(1, 1), Position::new(1, -1)],
+            Rotation::Down => [Position::new(-1, -1), Position::new(1, -1)],
+            Rotation::Left => [Position::new(-1, 1), Position::new(-1, -1)],
+        };
+        if front_corners
+            .iter()
+            .all(|corner| board.is_filled(piece.position + *corner))
+        {
+            TwistKind::Full
+        } else {
+            TwistKind::Mini
+        }
     }
 }
 
@@ -47,11 +88,11 @@ impl<TPieceSet: PieceSet> TwistDetector<TPieceSet> for AllTwistDetector {
             Position::down(),
         ];
 
-        return directions.iter().all(|direction| {
+        directions.iter().all(|direction| {
             board.is_obstructed(
                 piece_set.units(&piece.piece_type, &piece.rotation),
                 piece.position + *direction,
             )
-        });
+        })
     }
 }
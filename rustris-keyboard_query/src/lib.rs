@@ -15,15 +15,21 @@ impl KeyboardQueryInputSource {
         }
     }
 
-    fn is_press(&self, keys: &Vec<u16>, key: u16) -> bool {
+    fn is_press(&self, keys: &[u16], key: u16) -> bool {
         !self.prev_keys.contains(&key) && keys.contains(&key)
     }
 
-    fn is_release(&self, keys: &Vec<u16>, key: u16) -> bool {
+    fn is_release(&self, keys: &[u16], key: u16) -> bool {
         self.prev_keys.contains(&key) && !keys.contains(&key)
     }
 }
 
+impl Default for KeyboardQueryInputSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl InputSource for KeyboardQueryInputSource {
     fn inputs(&mut self) -> Vec<Input> {
         let keys = self.device_state.get_keys();
@@ -59,9 +65,18 @@ impl InputSource for KeyboardQueryInputSource {
             if self.is_press(&keys, 56) {
                 inputs.push(Input::Hold);
             }
+            if self.is_press(&keys, 32) {
+                inputs.push(Input::Undo);
+            }
+            if self.is_press(&keys, 15) {
+                inputs.push(Input::Redo);
+            }
             if self.is_press(&keys, 36) {
                 inputs.push(Input::Pause);
             }
+            if self.is_press(&keys, 53) {
+                inputs.push(Input::Quit);
+            }
         }
         self.prev_keys = keys;
         inputs
@@ -11,6 +11,12 @@ impl SrsPieceSet {
     }
 }
 
+impl Default for SrsPieceSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl PieceSet for SrsPieceSet {
     fn units(&self, piece_type: &PieceType, rotation: &Rotation) -> [Position; 4] {
         match piece_type {
@@ -46,7 +52,7 @@ impl PieceSet for SrsPieceSet {
             }
         }
 
-        return None;
+        None
     }
 }
 
@@ -0,0 +1,392 @@
+use std::{
+    collections::VecDeque,
+    error::Error,
+    fmt,
+    io::{self, BufRead, BufReader, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use rustris_bot::{Move, Planner};
+use rustris_core::{
+    board::Board,
+    finesse::finesse_path,
+    input::GameView,
+    piece::{Piece, PieceSet, PieceType, Rotation},
+    position::Position,
+};
+
+mod messages;
+
+pub use messages::Move as TbpMove;
+pub use messages::{BotMessage, Cell, FrontendMessage, Location, Orientation, Spin};
+
+/// How long a bot has to quit on its own before it's killed
+const QUIT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// An error that occurred while talking to a bot
+#[derive(Debug)]
+pub enum TbpError {
+    Io(io::Error),
+    Format(serde_json::Error),
+    /// The bot reported an error, e.g. because it can't play by the rules
+    Bot(String),
+    /// The bot didn't answer in time
+    Timeout,
+    /// The bot quit or closed its output
+    Disconnected,
+    /// The bot's answer wasn't what was asked for
+    Unexpected(BotMessage),
+    /// None of the moves the bot suggested can be played
+    NoValidMove,
+}
+
+impl fmt::Display for TbpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TbpError::Io(error) => write!(f, "could not talk to bot: {}", error),
+            TbpError::Format(error) => write!(f, "invalid message from bot: {}", error),
+            TbpError::Bot(reason) => write!(f, "bot error: {}", reason),
+            TbpError::Timeout => write!(f, "bot did not answer in time"),
+            TbpError::Disconnected => write!(f, "bot disconnected"),
+            TbpError::Unexpected(message) => {
+                write!(f, "unexpected message from bot: {:?}", message)
+            }
+            TbpError::NoValidMove => write!(f, "bot suggested no valid moves"),
+        }
+    }
+}
+
+impl Error for TbpError {}
+
+impl From<io::Error> for TbpError {
+    fn from(error: io::Error) -> Self {
+        TbpError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for TbpError {
+    fn from(error: serde_json::Error) -> Self {
+        if error.is_io() {
+            TbpError::Io(error.into())
+        } else {
+            TbpError::Format(error)
+        }
+    }
+}
+
+/// What a bot says about itself when it starts
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BotInfo {
+    pub name: String,
+    pub version: String,
+    pub author: String,
+    pub features: Vec<String>,
+}
+
+/// A bot process that speaks the Tetris Bot Protocol, with one JSON message per line on its
+/// standard input and output
+///
+/// The bot is sent quit when it's dropped, and killed if it doesn't exit soon after.
+pub struct TbpBot {
+    child: Child,
+    stdin: ChildStdin,
+    messages: Receiver<Result<BotMessage, TbpError>>,
+    info: BotInfo,
+    /// How long to wait for each answer from the bot
+    pub timeout: Duration,
+}
+
+impl TbpBot {
+    /// Starts the bot and waits for it to introduce itself
+    pub fn spawn(command: &mut Command) -> Result<Self, TbpError> {
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdin = child.stdin.take().ok_or(TbpError::Disconnected)?;
+        let stdout = child.stdout.take().ok_or(TbpError::Disconnected)?;
+
+        // The bot's output is read on its own thread, so waiting for it can time out
+        let (sender, messages) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let message = line
+                    .map_err(TbpError::from)
+                    .and_then(|line| Ok(serde_json::from_str(&line)?));
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut bot = Self {
+            child,
+            stdin,
+            messages,
+            info: BotInfo {
+                name: String::new(),
+                version: String::new(),
+                author: String::new(),
+                features: vec![],
+            },
+            timeout: Duration::from_secs(10),
+        };
+        match bot.receive()? {
+            BotMessage::Info {
+                name,
+                version,
+                author,
+                features,
+            } => {
+                bot.info = BotInfo {
+                    name,
+                    version,
+                    author,
+                    features,
+                };
+                Ok(bot)
+            }
+            message => Err(TbpError::Unexpected(message)),
+        }
+    }
+
+    pub fn info(&self) -> &BotInfo {
+        &self.info
+    }
+
+    pub fn send(&mut self, message: &FrontendMessage) -> Result<(), TbpError> {
+        serde_json::to_writer(&mut self.stdin, message)?;
+        self.stdin.write_all(b"\n")?;
+        self.stdin.flush()?;
+        Ok(())
+    }
+
+    /// Waits for the bot's next message, turning errors it reports into [TbpError::Bot] and
+    /// skipping messages this version of the protocol doesn't know
+    pub fn receive(&mut self) -> Result<BotMessage, TbpError> {
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            match self.messages.recv_timeout(timeout) {
+                Ok(Ok(BotMessage::Error { reason })) => return Err(TbpError::Bot(reason)),
+                Ok(Ok(BotMessage::Unknown)) => (),
+                Ok(message) => return message,
+                Err(RecvTimeoutError::Timeout) => return Err(TbpError::Timeout),
+                Err(RecvTimeoutError::Disconnected) => return Err(TbpError::Disconnected),
+            }
+        }
+    }
+}
+
+impl Drop for TbpBot {
+    fn drop(&mut self) {
+        if self.send(&FrontendMessage::Quit).is_ok() {
+            let deadline = Instant::now() + QUIT_TIMEOUT;
+            while Instant::now() < deadline {
+                if let Ok(Some(_)) = self.child.try_wait() {
+                    return;
+                }
+                thread::sleep(Duration::from_millis(10));
+            }
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// The game as the bot was told about it, used to tell when the bot needs to be started again
+struct Model {
+    board: Board,
+    hold: Option<PieceType>,
+    /// The pieces the bot knows about, starting with the active piece
+    queue: VecDeque<PieceType>,
+}
+
+/// A planner that asks an external bot where to place each piece
+///
+/// The bot is sent the rules and the game's state before the first piece, then told about each
+/// move played and each piece added to the preview. If the game stops matching what the bot was
+/// told, e.g. because garbage was received or a piece went back to spawn, the bot is started
+/// again from the game's state.
+///
+/// Moves are only played if the piece can reach them from where it is. Once anything goes wrong
+/// the planner stops planning, leaving pieces to drop where they are, and [error] returns why.
+pub struct TbpPlanner {
+    bot: TbpBot,
+    ready: bool,
+    model: Option<Model>,
+    error: Option<TbpError>,
+}
+
+impl TbpPlanner {
+    pub fn new(bot: TbpBot) -> Self {
+        Self {
+            bot,
+            ready: false,
+            model: None,
+            error: None,
+        }
+    }
+
+    pub fn bot(&self) -> &TbpBot {
+        &self.bot
+    }
+
+    /// The error that stopped the planner, if there was one
+    pub fn error(&self) -> Option<&TbpError> {
+        self.error.as_ref()
+    }
+
+    /// Brings the bot up to date with the game
+    fn sync(&mut self, view: &GameView, queue: &[PieceType]) -> Result<(), TbpError> {
+        if !self.ready {
+            self.bot.send(&FrontendMessage::Rules {})?;
+            match self.bot.receive()? {
+                BotMessage::Ready => self.ready = true,
+                message => return Err(TbpError::Unexpected(message)),
+            }
+        }
+
+        let in_sync = self.model.as_ref().is_some_and(|model| {
+            model.board.rows == view.board.rows
+                && model.hold == view.hold_piece_type
+                && model.queue.len() <= queue.len()
+                && model.queue.iter().zip(queue).all(|(a, b)| a == b)
+        });
+        if let Some(model) = self.model.as_mut().filter(|_| in_sync) {
+            for piece in &queue[model.queue.len()..] {
+                self.bot
+                    .send(&FrontendMessage::NewPiece { piece: *piece })?;
+                model.queue.push_back(*piece);
+            }
+            return Ok(());
+        }
+
+        if self.model.is_some() {
+            self.bot.send(&FrontendMessage::Stop)?;
+        }
+        self.bot.send(&FrontendMessage::Start {
+            hold: view.hold_piece_type,
+            queue: queue.to_vec(),
+            combo: view.consecutive_clears,
+            back_to_back: view.back_to_back,
+            board: FrontendMessage::board(view.board),
+        })?;
+        self.model = Some(Model {
+            board: view.board.clone(),
+            hold: view.hold_piece_type,
+            queue: queue.iter().copied().collect(),
+        });
+        Ok(())
+    }
+
+    /// Asks the bot for its moves and plays the first one that can be reached
+    fn suggest<TPieceSet: PieceSet>(
+        &mut self,
+        view: &GameView,
+        piece_set: &TPieceSet,
+        active_piece: Piece,
+    ) -> Result<Move, TbpError> {
+        self.bot.send(&FrontendMessage::Suggest)?;
+        let moves = match self.bot.receive()? {
+            BotMessage::Suggestion { moves } => moves,
+            message => return Err(TbpError::Unexpected(message)),
+        };
+
+        let (played, chosen) = moves
+            .into_iter()
+            .find_map(|played| {
+                valid_move(view, piece_set, active_piece, &played.location)
+                    .map(|chosen| (played, chosen))
+            })
+            .ok_or(TbpError::NoValidMove)?;
+        self.bot.send(&FrontendMessage::Play { played })?;
+
+        // The bot plays the move too, so the model follows it
+        if let Some(model) = self.model.as_mut() {
+            model.board.lock_piece(
+                piece_set.units(&chosen.piece.piece_type, &chosen.piece.rotation),
+                chosen.piece.position,
+            );
+            let current = model.queue.pop_front();
+            if chosen.hold {
+                if model.hold.is_none() {
+                    model.queue.pop_front();
+                }
+                model.hold = current;
+            }
+        }
+        Ok(chosen)
+    }
+}
+
+impl<TPieceSet: PieceSet> Planner<TPieceSet> for TbpPlanner {
+    fn plan(&mut self, view: &GameView, piece_set: &TPieceSet) -> Option<Move> {
+        if self.error.is_some() {
+            return None;
+        }
+        let active_piece = view.active_piece?;
+        let queue: Vec<PieceType> = [active_piece.piece_type]
+            .into_iter()
+            .chain(view.next_piece_types.iter().copied())
+            .collect();
+
+        let result = self
+            .sync(view, &queue)
+            .and_then(|_| self.suggest(view, piece_set, active_piece));
+        match result {
+            Ok(chosen) => Some(chosen),
+            Err(error) => {
+                self.error = Some(error);
+                None
+            }
+        }
+    }
+}
+
+/// The move that places a piece at the location, if it rests there and the piece can reach it
+fn valid_move(
+    view: &GameView,
+    piece_set: &dyn PieceSet,
+    active_piece: Piece,
+    location: &Location,
+) -> Option<Move> {
+    let hold = if location.piece_type == active_piece.piece_type {
+        false
+    } else if view.rules.hold_enabled && view.can_hold {
+        let from_hold = view
+            .hold_piece_type
+            .or_else(|| view.next_piece_types.first().copied());
+        if from_hold != Some(location.piece_type) {
+            return None;
+        }
+        true
+    } else {
+        return None;
+    };
+
+    // The piece that comes out of hold spawns
+    let start = if hold {
+        Piece {
+            piece_type: location.piece_type,
+            rotation: Rotation::Up,
+            position: view.rules.spawn_position,
+        }
+    } else {
+        active_piece
+    };
+    let piece = location.to_piece(piece_set);
+    let units = piece_set.units(&piece.piece_type, &piece.rotation);
+    if view.board.is_obstructed(units, piece.position)
+        || !view
+            .board
+            .is_obstructed(units, piece.position + Position::down())
+    {
+        return None;
+    }
+    finesse_path(view.board, piece_set, view.rules, start, &piece)?;
+    Some(Move { hold, piece })
+}
@@ -0,0 +1,196 @@
+use serde::{Deserialize, Serialize};
+
+use rustris_core::{
+    board::Board,
+    piece::{Piece, PieceSet, PieceType, Rotation},
+    position::Position,
+};
+
+/// A message the frontend sends to the bot
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FrontendMessage {
+    /// Asks the bot whether it can play by the rules, which it answers with ready or an error
+    Rules {},
+    /// Starts the bot thinking about a game from the given state
+    Start {
+        hold: Option<PieceType>,
+        /// The pieces the bot knows are coming, starting with the active piece
+        queue: Vec<PieceType>,
+        combo: u32,
+        back_to_back: bool,
+        /// The board's rows from the bottom up, with the piece each cell was filled by
+        board: Vec<[Option<Cell>; 10]>,
+    },
+    /// Stops the bot thinking about the game, until it's started again
+    Stop,
+    /// Asks the bot for its suggested moves for the active piece
+    Suggest,
+    /// Tells the bot the move that was played, which it should play too
+    Play {
+        #[serde(rename = "move")]
+        played: Move,
+    },
+    /// Tells the bot about a piece that was added to the end of the queue
+    NewPiece {
+        piece: PieceType,
+    },
+    Quit,
+}
+
+/// What filled a cell of the board
+///
+/// Boards only record whether each cell is filled, so every filled cell is sent as garbage.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Cell {
+    #[serde(rename = "G")]
+    Garbage,
+}
+
+impl FrontendMessage {
+    /// The board in the form sent by [FrontendMessage::Start]
+    pub fn board(board: &Board) -> Vec<[Option<Cell>; 10]> {
+        board
+            .rows
+            .iter()
+            .map(|row| row.map(|filled| filled.then_some(Cell::Garbage)))
+            .collect()
+    }
+}
+
+/// A message the bot sends to the frontend
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BotMessage {
+    /// Introduces the bot as soon as it starts
+    Info {
+        name: String,
+        version: String,
+        author: String,
+        #[serde(default)]
+        features: Vec<String>,
+    },
+    /// The bot can play by the rules it was sent
+    Ready,
+    /// The bot can't carry on, e.g. because it doesn't support the rules
+    Error { reason: String },
+    /// The moves the bot suggests for the active piece, best first
+    Suggestion { moves: Vec<Move> },
+    /// Messages from newer versions of the protocol are ignored
+    #[serde(other)]
+    Unknown,
+}
+
+/// A placement of a piece, as suggested by a bot
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Move {
+    pub location: Location,
+    pub spin: Spin,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Spin {
+    None,
+    Mini,
+    Full,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Orientation {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl From<Rotation> for Orientation {
+    fn from(rotation: Rotation) -> Self {
+        match rotation {
+            Rotation::Up => Orientation::North,
+            Rotation::Right => Orientation::East,
+            Rotation::Down => Orientation::South,
+            Rotation::Left => Orientation::West,
+        }
+    }
+}
+
+impl From<Orientation> for Rotation {
+    fn from(orientation: Orientation) -> Self {
+        match orientation {
+            Orientation::North => Rotation::Up,
+            Orientation::East => Rotation::Right,
+            Orientation::South => Rotation::Down,
+            Orientation::West => Rotation::Left,
+        }
+    }
+}
+
+/// Where a piece is, given by the cell the piece rotates around
+///
+/// The protocol rotates every piece around a single cell, including the I and O pieces, which
+/// piece sets like SRS move as they rotate. Locations are converted to and from pieces by the
+/// cells they fill, assuming the piece set's upright pieces are the protocol's north pieces.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Location {
+    #[serde(rename = "type")]
+    pub piece_type: PieceType,
+    pub orientation: Orientation,
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Location {
+    pub fn from_piece(piece_set: &dyn PieceSet, piece: &Piece) -> Self {
+        let orientation = Orientation::from(piece.rotation);
+        let cells = sorted(
+            piece_set
+                .units(&piece.piece_type, &piece.rotation)
+                .map(|unit| unit + piece.position),
+        );
+        let units = units(piece_set, piece.piece_type, orientation);
+        let center = cells[0] - units[0];
+        Self {
+            piece_type: piece.piece_type,
+            orientation,
+            x: center.x,
+            y: center.y,
+        }
+    }
+
+    /// The piece in the piece set that fills the same cells
+    pub fn to_piece(&self, piece_set: &dyn PieceSet) -> Piece {
+        let rotation = Rotation::from(self.orientation);
+        let cells = units(piece_set, self.piece_type, self.orientation)
+            .map(|unit| unit + Position::new(self.x, self.y));
+        let units = sorted(piece_set.units(&self.piece_type, &rotation));
+        Piece {
+            piece_type: self.piece_type,
+            rotation,
+            position: cells[0] - units[0],
+        }
+    }
+}
+
+/// The cells a piece fills around its center in the protocol, from the bottom left
+fn units(
+    piece_set: &dyn PieceSet,
+    piece_type: PieceType,
+    orientation: Orientation,
+) -> [Position; 4] {
+    let units = piece_set
+        .units(&piece_type, &Rotation::Up)
+        .map(|unit| match orientation {
+            Orientation::North => unit,
+            Orientation::East => Position::new(unit.y, -unit.x),
+            Orientation::South => Position::new(-unit.x, -unit.y),
+            Orientation::West => Position::new(-unit.y, unit.x),
+        });
+    sorted(units)
+}
+
+fn sorted(mut cells: [Position; 4]) -> [Position; 4] {
+    cells.sort_by_key(|cell| (cell.y, cell.x));
+    cells
+}
@@ -11,7 +11,7 @@ use termion::{
 use rustris_core::{
     game::Message,
     piece::{PieceSet, PieceType, Rotation},
-    renderer::Renderer,
+    renderer::{HudEntry, Renderer},
 };
 use rustris_core::{position::Position, renderer::RenderState};
 
@@ -83,6 +83,12 @@ impl TermionRenderer {
     }
 }
 
+impl Default for TermionRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<TPieceSet: PieceSet> Renderer<TPieceSet> for TermionRenderer {
     fn init(&mut self) {
         write!(self.stdout, "{}", Hide).unwrap();
@@ -291,6 +297,56 @@ impl<TPieceSet: PieceSet> Renderer<TPieceSet> for TermionRenderer {
             }
         }
 
+        if let Some(message_text) = state.outcome_text {
+            for (j, char) in message_text.chars().enumerate() {
+                render_ir[[
+                    board_start_y + (BOARD_HEIGHT * CELL_HEIGHT) / 2,
+                    board_start_x + (BOARD_WIDTH * CELL_WIDTH) / 2 - message_text.len() / 2 + j,
+                ]] = char;
+            }
+        }
+
+        // Render the mode's statistics below the next queue, putting the value on its own line
+        // when it doesn't fit next to the label, and dropping whatever doesn't fit on the screen
+        let hud_width = RIGHT_CONTENT_WIDTH * CELL_WIDTH;
+        let mut hud = state.hud.to_vec();
+        hud.push(HudEntry::new("FAULTS", state.stats.finesse_faults));
+        if let Some(history) = state.history {
+            hud.push(HudEntry::new(
+                "UNDO",
+                format!("{}/{}", history.placement, history.placements),
+            ));
+        }
+        let mut hud_lines = vec![];
+        for entry in &hud {
+            if entry.value.is_empty() {
+                hud_lines.push(entry.label.to_string());
+            } else if entry.label.len() + 1 + entry.value.len() <= hud_width {
+                hud_lines.push(format!("{} {}", entry.label, entry.value));
+            } else {
+                hud_lines.push(entry.label.to_string());
+                hud_lines.push(entry.value.to_string());
+            }
+        }
+
+        let hud_start_y = board_start_y + 17;
+        for (i, line) in hud_lines
+            .iter()
+            .take(render_height.saturating_sub(hud_start_y))
+            .enumerate()
+        {
+            for (j, char) in line.chars().take(hud_width).enumerate() {
+                render_ir[[hud_start_y + i, right_content_start_x + j]] = char;
+            }
+        }
+
+        // Only keep the newest messages that fit below the hold piece
+        let max_messages = render_height.saturating_sub(board_start_y + 7).div_ceil(2);
+        if self.message_states.len() > max_messages {
+            self.message_states
+                .drain(..self.message_states.len() - max_messages);
+        }
+
         for i in 0..self.message_states.len() {
             let message_state = self.message_states[i];
             self.message_states[i].timer -= delta_time;
@@ -308,6 +364,8 @@ impl<TPieceSet: PieceSet> Renderer<TPieceSet> for TermionRenderer {
                 Message::TwistSingle(_) => "TWIST SINGLE".to_string(),
                 Message::TwistDouble(_) => "TWIST DOUBLE".to_string(),
                 Message::TwistTriple(_) => "TWIST TRIPLE".to_string(),
+                Message::Attack(lines) => format!("SENT {}", lines),
+                Message::FinesseFault(presses) => format!("FINESSE +{}", presses),
                 Message::AllClear => continue,
             };
 
@@ -317,12 +375,16 @@ impl<TPieceSet: PieceSet> Renderer<TPieceSet> for TermionRenderer {
             }
         }
 
-        self.message_states = self
-            .message_states
-            .to_vec()
-            .into_iter()
-            .filter(|message_state| message_state.timer > 0f64)
-            .collect();
+        // Render the garbage meter along the left border, rising from the bottom of the board
+        for i in 0..(state.pending_garbage as usize).min(BOARD_HEIGHT) {
+            render_ir[[
+                board_start_y + BOARD_HEIGHT - 1 - i,
+                LEFT_CONTENT_WIDTH * CELL_WIDTH - 1,
+            ]] = '|';
+        }
+
+        self.message_states
+            .retain(|message_state| message_state.timer > 0f64);
 
         // Create a string builder for the final render
         let mut render = String::new();
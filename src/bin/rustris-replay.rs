@@ -0,0 +1,280 @@
+extern crate rustris_core;
+
+use std::{
+    env,
+    fs::File,
+    io::{stdout, Stdout, Write},
+};
+
+use game_loop::game_loop;
+use rustris_core::{
+    game::{Keyframe, Rustris},
+    piece::PieceType,
+    random::SequenceRandom,
+    replay::{Replay, ReplayError, ReplayInputActions},
+    twist::AllTwistDetector,
+};
+use rustris_srs::SrsPieceSet;
+use rustris_termion::TermionRenderer;
+use termion::{
+    async_stdin,
+    clear::CurrentLine,
+    cursor::{Goto, Show},
+    event::Key,
+    input::{Keys, TermRead},
+    AsyncReader,
+};
+
+/// The playback speeds the viewer can switch between
+const SPEEDS: [f64; 6] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+const NORMAL_SPEED: usize = 2;
+
+/// A keyframe is stored every this many frames, which is 10 seconds at 60 frames per second
+///
+/// Keyframes go by frame rather than by pieces locked, since undo takes pieces back. They don't
+/// keep the undo history, so replays played with undo only keep the first keyframe, from before
+/// anything could be undone.
+const KEYFRAME_INTERVAL: u64 = 600;
+
+/// The terminal row the status line is drawn on, just below the game
+const STATUS_ROW: u16 = 25;
+
+type ReplayGame = Rustris<
+    SrsPieceSet,
+    SequenceRandom<PieceType>,
+    ReplayInputActions,
+    TermionRenderer,
+    AllTwistDetector,
+>;
+
+struct ReplayViewer {
+    game: ReplayGame,
+    frame_time: f64,
+    seed: Option<u64>,
+    /// The keyframe at index i is frame i * KEYFRAME_INTERVAL
+    keyframes: Vec<Keyframe<SequenceRandom<PieceType>, ReplayInputActions>>,
+    /// The frame at index i is the first where i pieces had been locked
+    piece_frames: Vec<u64>,
+    keys: Keys<AsyncReader>,
+    stdout: Stdout,
+    speed: usize,
+    paused: bool,
+    playback_timer: f64,
+    seek_target: String,
+    quit: bool,
+}
+
+impl ReplayViewer {
+    fn new(replay: &Replay) -> Self {
+        let mut game = Rustris::new(
+            replay.rules.clone(),
+            SrsPieceSet,
+            replay.random(),
+            ReplayInputActions::new(replay),
+            AllTwistDetector,
+            TermionRenderer::new(),
+        );
+        game.set_replay(replay);
+        game.enable_undo();
+        game.init();
+        let keyframes = vec![game.keyframe()];
+
+        Self {
+            game,
+            frame_time: replay.frame_time,
+            seed: replay.seed,
+            keyframes,
+            piece_frames: vec![0],
+            keys: async_stdin().keys(),
+            stdout: stdout(),
+            speed: NORMAL_SPEED,
+            paused: false,
+            playback_timer: 0f64,
+            seek_target: String::new(),
+            quit: false,
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.game.input_actions().is_finished()
+    }
+
+    /// The piece that is currently active, counting from 1
+    fn current_piece(&self) -> u32 {
+        self.game.pieces_locked() + 1
+    }
+
+    fn frame(&self) -> u64 {
+        self.game.input_actions().frame()
+    }
+
+    /// Plays a single frame of the replay, storing a keyframe if a new interval was reached and
+    /// noting the first frame each piece is active
+    fn step(&mut self) {
+        if self.is_finished() {
+            return;
+        }
+        self.game.update(self.frame_time);
+        let frame = self.frame();
+        while self.game.rules().undo_limit == 0
+            && self.keyframes.len() as u64 <= frame / KEYFRAME_INTERVAL
+        {
+            self.keyframes.push(self.game.keyframe());
+        }
+        while self.piece_frames.len() as u32 <= self.game.pieces_locked() {
+            self.piece_frames.push(frame);
+        }
+    }
+
+    /// Moves playback to the given frame, from the closest keyframe before it if that's nearer
+    fn seek_frame(&mut self, frame: u64) {
+        let index = ((frame / KEYFRAME_INTERVAL) as usize).min(self.keyframes.len() - 1);
+        let keyframe_frame = index as u64 * KEYFRAME_INTERVAL;
+        if self.frame() > frame || self.frame() < keyframe_frame {
+            self.game.restore_keyframe(&self.keyframes[index]);
+        }
+        while self.frame() < frame && !self.is_finished() {
+            self.step();
+        }
+    }
+
+    /// Moves playback to the first frame where the given piece (counting from 1) is active
+    fn seek(&mut self, piece: u32) {
+        let pieces_locked = (piece.max(1) - 1) as usize;
+        match self.piece_frames.get(pieces_locked) {
+            Some(frame) => self.seek_frame(*frame),
+            None => {
+                // Pieces that haven't been reached yet are found by playing on from the last
+                // keyframe, which is as far as playback has been
+                let last_frame = (self.keyframes.len() as u64 - 1) * KEYFRAME_INTERVAL;
+                self.seek_frame(last_frame.max(self.frame()));
+                while self.piece_frames.len() <= pieces_locked && !self.is_finished() {
+                    self.step();
+                }
+            }
+        }
+        self.playback_timer = 0f64;
+    }
+
+    fn handle_keys(&mut self) {
+        while let Some(Ok(key)) = self.keys.next() {
+            match key {
+                Key::Char(' ') => self.paused = !self.paused,
+                Key::Char('+') | Key::Char('=') => {
+                    self.speed = (self.speed + 1).min(SPEEDS.len() - 1)
+                }
+                Key::Char('-') => self.speed = self.speed.saturating_sub(1),
+                Key::Char('.') | Key::Right if self.paused => self.step(),
+                Key::Char(']') => self.seek(self.current_piece() + 1),
+                Key::Char('[') => self.seek(self.current_piece().saturating_sub(1)),
+                Key::Char(c) if c.is_ascii_digit() => self.seek_target.push(c),
+                Key::Backspace => {
+                    self.seek_target.pop();
+                }
+                Key::Char('\n') | Key::Char('g') => {
+                    if let Ok(piece) = self.seek_target.parse::<u32>() {
+                        self.seek(piece);
+                    }
+                    self.seek_target.clear();
+                }
+                Key::Char('q') | Key::Esc => self.quit = true,
+                _ => (),
+            }
+        }
+    }
+
+    fn update(&mut self, delta_time: f64) {
+        self.handle_keys();
+
+        if self.paused {
+            return;
+        }
+
+        self.playback_timer += delta_time * SPEEDS[self.speed];
+        while self.playback_timer >= self.frame_time && !self.is_finished() {
+            self.playback_timer -= self.frame_time;
+            self.step();
+        }
+
+        // Stop at the end so the final board can be reviewed
+        if self.is_finished() {
+            self.paused = true;
+            self.playback_timer = 0f64;
+        }
+    }
+
+    fn render(&mut self, delta_time: f64) {
+        self.game.render(delta_time);
+
+        let input_actions = self.game.input_actions();
+        let status = format!(
+            "{}  {}PIECE {}  FRAME {}/{}  SPEED {}x{}{}",
+            self.game.mode().name().to_uppercase(),
+            match self.seed {
+                Some(seed) => format!("SEED {}  ", seed),
+                None => "".to_string(),
+            },
+            self.current_piece(),
+            input_actions.frame(),
+            input_actions.frames(),
+            SPEEDS[self.speed],
+            if self.paused { "  PAUSED" } else { "" },
+            if self.seek_target.is_empty() {
+                "".to_string()
+            } else {
+                format!("  GO TO PIECE {}", self.seek_target)
+            },
+        );
+        let help = "space pause  -/+ speed  . step  [/] piece  0-9 enter go to piece  q quit";
+
+        write!(
+            self.stdout,
+            "{}{}{}{}{}{}",
+            Goto(1, STATUS_ROW),
+            CurrentLine,
+            status,
+            Goto(1, STATUS_ROW + 1),
+            CurrentLine,
+            help
+        )
+        .unwrap();
+        self.stdout.flush().unwrap();
+    }
+}
+
+fn main() {
+    let path = match env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: rustris-replay <replay file>");
+            return;
+        }
+    };
+
+    let replay = match File::open(&path)
+        .map_err(ReplayError::from)
+        .and_then(Replay::load)
+    {
+        Ok(replay) => replay,
+        Err(error) => {
+            eprintln!("Failed to load replay {}: {}", path, error);
+            return;
+        }
+    };
+
+    // Play the replay back in real time, taking the viewer controls between frames
+    let mut game_loop = game_loop(
+        ReplayViewer::new(&replay),
+        60,
+        1.0,
+        |g| {
+            g.game.update(g.fixed_time_step());
+            if g.game.quit {
+                g.exit();
+            }
+        },
+        |g| g.game.render(g.fixed_time_step()),
+    );
+
+    write!(game_loop.game.stdout, "{}\r\n", Show).unwrap();
+}
@@ -0,0 +1,125 @@
+extern crate rustris_core;
+
+use std::{env, process::Command, time::Instant};
+
+use rustris_bot::BotInputActions;
+use rustris_core::{
+    game::Rustris,
+    input::Handling,
+    piece::PieceType,
+    random::{random_seed, RandomBag},
+    renderer::NullRenderer,
+    rules::RuleSet,
+    twist::AllTwistDetector,
+};
+use rustris_srs::SrsPieceSet;
+use rustris_tbp::{TbpBot, TbpPlanner};
+
+const USAGE: &str = "usage: rustris-tbp [--pieces <pieces>] [--seed <seed>] [--rules <guideline|classic|arcade>] <bot command> [<bot args>...]";
+
+/// The length of each simulated frame
+const FRAME: f64 = 1f64 / 60f64;
+
+/// Plays a game with an external Tetris Bot Protocol bot, as fast as the bot can think, and
+/// prints how it did
+fn main() {
+    let mut pieces = 100;
+    let mut seed = None;
+    let mut rules = RuleSet::guideline();
+    let mut args = env::args().skip(1);
+    let program = loop {
+        let Some(arg) = args.next() else {
+            eprintln!("{}", USAGE);
+            return;
+        };
+        if !arg.starts_with("--") {
+            break arg;
+        }
+        match (arg.as_str(), args.next()) {
+            ("--pieces", Some(value)) => match value.parse::<u32>() {
+                Ok(value) => pieces = value,
+                Err(_) => {
+                    eprintln!("invalid piece count '{}'\n{}", value, USAGE);
+                    return;
+                }
+            },
+            ("--seed", Some(value)) => match value.parse::<u64>() {
+                Ok(value) => seed = Some(value),
+                Err(_) => {
+                    eprintln!("invalid seed '{}'\n{}", value, USAGE);
+                    return;
+                }
+            },
+            ("--rules", Some(value)) => match value.parse::<RuleSet>() {
+                Ok(value) => rules = value,
+                Err(error) => {
+                    eprintln!("{}\n{}", error, USAGE);
+                    return;
+                }
+            },
+            _ => {
+                eprintln!("{}", USAGE);
+                return;
+            }
+        }
+    };
+
+    let bot = match TbpBot::spawn(Command::new(&program).args(args)) {
+        Ok(bot) => bot,
+        Err(error) => {
+            eprintln!("Failed to start bot {}: {}", program, error);
+            return;
+        }
+    };
+    let info = bot.info().clone();
+    println!("{} {} by {}", info.name, info.version, info.author);
+
+    // The bot presses keys instantly, so each piece takes only as long as the bot thinks
+    let seed = seed.unwrap_or_else(random_seed);
+    let mut game = Rustris::new(
+        rules,
+        SrsPieceSet,
+        RandomBag::with_seed(PieceType::all(), seed),
+        BotInputActions::new(
+            SrsPieceSet,
+            TbpPlanner::new(bot),
+            Handling {
+                delayed_auto_shift: 0f64,
+                auto_repeat_rate: 0f64,
+            },
+            0f64,
+        ),
+        AllTwistDetector,
+        NullRenderer,
+    );
+    game.init();
+
+    let start = Instant::now();
+    while game.pieces_locked() < pieces && game.outcome().is_none() {
+        game.update(FRAME);
+        if let Some(error) = game.input_actions().planner().error() {
+            eprintln!("Stopped after {} pieces: {}", game.pieces_locked(), error);
+            break;
+        }
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+
+    let stats = game.stats();
+    println!(
+        "Seed: {}  Pieces: {}  Lines: {}  Attack: {}  Score: {}  Faults: {}",
+        seed,
+        game.pieces_locked(),
+        stats.lines_cleared,
+        stats.attack_sent,
+        stats.score,
+        stats.finesse_faults
+    );
+    println!(
+        "{:.2} pieces per second, {:.2} attack per piece",
+        game.pieces_locked() as f64 / elapsed,
+        stats.attack_sent as f64 / game.pieces_locked().max(1) as f64
+    );
+    if let Some(outcome) = game.outcome() {
+        println!("Game over: {:?}", outcome);
+    }
+}
@@ -1,45 +1,344 @@
 extern crate rustris_core;
 
+use std::{env, fs, fs::File, time::Duration};
+
 use game_loop::game_loop;
+use rustris_bot::{BotInputActions, HeuristicPlanner};
 use rustris_core::{
-    game::Rustris, input::DasInputActions, piece::PieceType, queue::Queue, random::RandomBag,
+    game::Rustris,
+    input::{Action, DasInputActions, GameView, Handling, InputActions},
+    mode::{format_time, Mode},
+    puzzle::{Puzzle, PuzzleGoal},
+    random::{random_seed, PieceRandom, Randomizer, SequenceRandom},
+    replay::ReplayError,
+    rules::RuleSet,
+    snapshot::{GameSnapshot, SnapshotError},
     twist::AllTwistDetector,
 };
 use rustris_keyboard_query::KeyboardQueryInputSource;
 use rustris_srs::SrsPieceSet;
 use rustris_termion::TermionRenderer;
 
+const USAGE: &str = "usage: rustris [--mode <endless|sprint|ultra|marathon|endless-marathon|cheese|survival|arcade>] [--rules <guideline|classic|arcade>] [--lines <line goal>] [--time <seconds>] [--seed <seed>] [--randomizer <bag|14-bag|pure|history|nes>] [--record <replay file>] [--puzzle <puzzle file>] [--save <snapshot file>] [--resume <snapshot file>] [--undo <placements>] [--finesse-training <on|off>] [--player <human|bot>]";
+
+/// The seconds the bot waits between key presses
+const BOT_PRESS_DELAY: f64 = 3f64 / 60f64;
+
+/// Who is playing the game
+enum Player {
+    Human(DasInputActions<KeyboardQueryInputSource>),
+    /// The built-in bot, with the keyboard still able to pause and quit
+    Bot {
+        bot: Box<BotInputActions<SrsPieceSet, HeuristicPlanner<AllTwistDetector>>>,
+        keyboard: DasInputActions<KeyboardQueryInputSource>,
+    },
+}
+
+impl InputActions for Player {
+    fn observe(&mut self, view: &GameView) {
+        if let Player::Bot { bot, .. } = self {
+            bot.observe(view);
+        }
+    }
+
+    fn actions(&mut self, delta_time: f64) -> Vec<Action> {
+        match self {
+            Player::Human(keyboard) => keyboard.actions(delta_time),
+            Player::Bot { bot, keyboard } => {
+                let mut actions: Vec<Action> = keyboard
+                    .actions(delta_time)
+                    .into_iter()
+                    .filter(|action| matches!(action, Action::Pause | Action::Quit))
+                    .collect();
+                // The bot waits out the frame the game is paused or unpaused on, so none of its
+                // presses are lost
+                if !actions.contains(&Action::Pause) {
+                    actions.extend(bot.actions(delta_time));
+                }
+                actions
+            }
+        }
+    }
+
+    fn handling(&self) -> Option<Handling> {
+        match self {
+            Player::Human(keyboard) => keyboard.handling(),
+            Player::Bot { bot, .. } => bot.handling(),
+        }
+    }
+}
+
 fn main() {
+    // Games with the same seed are dealt the same pieces
+    // A replay of the game is written to the record path when the game is quit
+    // A game that's quit before it ends is written to the save path, to be resumed later
+    let mut mode = Mode::Endless;
+    let mut rules = None;
+    let mut line_goal = None;
+    let mut time_limit = None;
+    let mut seed = None;
+    let mut randomizer = Randomizer::Bag;
+    let mut record_path = None;
+    let mut puzzle = None;
+    let mut save_path = None;
+    let mut undo_limit = None;
+    let mut finesse_training = None;
+    let mut bot = false;
+    let mut snapshot: Option<GameSnapshot<PieceRandom>> = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            ("--mode", Some(value)) => match value.parse::<Mode>() {
+                Ok(value) => mode = value,
+                Err(error) => {
+                    eprintln!("{}\n{}", error, USAGE);
+                    return;
+                }
+            },
+            ("--rules", Some(value)) => match value.parse::<RuleSet>() {
+                Ok(value) => rules = Some(value),
+                Err(error) => {
+                    eprintln!("{}\n{}", error, USAGE);
+                    return;
+                }
+            },
+            ("--lines", Some(value)) => match value.parse::<u32>() {
+                Ok(value) => line_goal = Some(value),
+                Err(_) => {
+                    eprintln!("invalid line goal '{}'\n{}", value, USAGE);
+                    return;
+                }
+            },
+            ("--time", Some(value)) => match value.parse::<f64>() {
+                Ok(value) if value > 0f64 => time_limit = Some(Duration::from_secs_f64(value)),
+                _ => {
+                    eprintln!("invalid time limit '{}'\n{}", value, USAGE);
+                    return;
+                }
+            },
+            ("--seed", Some(value)) => match value.parse::<u64>() {
+                Ok(value) => seed = Some(value),
+                Err(_) => {
+                    eprintln!("invalid seed '{}'\n{}", value, USAGE);
+                    return;
+                }
+            },
+            ("--randomizer", Some(value)) => match value.parse::<Randomizer>() {
+                Ok(value) => randomizer = value,
+                Err(error) => {
+                    eprintln!("{}\n{}", error, USAGE);
+                    return;
+                }
+            },
+            ("--record", Some(path)) => record_path = Some(path),
+            ("--puzzle", Some(path)) => match fs::read_to_string(&path)
+                .map_err(|error| error.to_string())
+                .and_then(|text| Puzzle::parse(&text).map_err(|error| error.to_string()))
+            {
+                Ok(value) => puzzle = Some(value),
+                Err(error) => {
+                    eprintln!("Failed to load puzzle {}: {}", path, error);
+                    return;
+                }
+            },
+            ("--undo", Some(value)) => match value.parse::<usize>() {
+                Ok(value) => undo_limit = Some(value),
+                Err(_) => {
+                    eprintln!("invalid undo limit '{}'\n{}", value, USAGE);
+                    return;
+                }
+            },
+            ("--finesse-training", Some(value)) => match value.as_str() {
+                "on" => finesse_training = Some(true),
+                "off" => finesse_training = Some(false),
+                _ => {
+                    eprintln!("invalid finesse training '{}'\n{}", value, USAGE);
+                    return;
+                }
+            },
+            ("--player", Some(value)) => match value.as_str() {
+                "human" => bot = false,
+                "bot" => bot = true,
+                _ => {
+                    eprintln!("invalid player '{}'\n{}", value, USAGE);
+                    return;
+                }
+            },
+            ("--save", Some(path)) => save_path = Some(path),
+            ("--resume", Some(path)) => {
+                match File::open(&path)
+                    .map_err(SnapshotError::from)
+                    .and_then(GameSnapshot::load)
+                {
+                    Ok(value) => snapshot = Some(value),
+                    Err(error) => {
+                        eprintln!("Failed to resume {}: {}", path, error);
+                        return;
+                    }
+                }
+            }
+            _ => {
+                eprintln!("{}", USAGE);
+                return;
+            }
+        }
+    }
+
+    match (&mut mode, line_goal, time_limit) {
+        (Mode::Sprint { lines }, Some(line_goal), _) => *lines = line_goal,
+        (Mode::Marathon { lines: Some(lines) }, Some(line_goal), _) => *lines = line_goal,
+        (Mode::Cheese { lines, .. }, Some(line_goal), _) => *lines = line_goal,
+        (Mode::Ultra { duration }, _, Some(time_limit)) => *duration = time_limit,
+        _ => (),
+    }
+
+    // Resumed games play the mode they were saved from, whose goal is restored from the
+    // snapshot along with the rules
+    if let Some(snapshot) = &snapshot {
+        mode = match snapshot.mode.as_str() {
+            "puzzle" => Mode::Puzzle {
+                goal: PuzzleGoal::AllClear,
+            },
+            name => match name.parse::<Mode>() {
+                Ok(value) => value,
+                Err(error) => {
+                    eprintln!("Failed to resume: {}", error);
+                    return;
+                }
+            },
+        };
+        rules = Some(snapshot.rules.clone());
+        puzzle = None;
+    }
+
+    // Arcade games need the arcade rules for their gravity to follow the level
+    let mut rules = rules.unwrap_or_else(|| match mode {
+        Mode::Arcade => RuleSet::arcade(),
+        _ => RuleSet::guideline(),
+    });
+    // Practicing with undo is opt in, since it changes the rules of the game
+    if let Some(undo_limit) = undo_limit {
+        rules.undo_limit = undo_limit;
+    }
+    // So is finesse training, which makes pieces placed with extra key presses go back to spawn
+    if let Some(finesse_training) = finesse_training {
+        rules.finesse_training = finesse_training;
+    }
+
     // Create a new rustris game simulation using:
+    // - The chosen rule set (the guideline rules by default, or the arcade rules for arcade)
     // - Super Rotation System (SRS)
-    // - A next queue showing 5 pieces
-    // - A random bag generator
+    // - The chosen piece randomizer (a random bag by default), or the puzzle's pieces
     // - Delayed Auto Shift (DAS) input
-    // - An input source implementation that uses the keyboard_query crate
+    // - An input source implementation that uses the keyboard_query crate, or the built-in bot
+    //   with the same handling
     // - A twist detector that detects all twists for all piece types
     // - A rendering implementation that uses the termion crate
+    let random = match &puzzle {
+        Some(puzzle) => PieceRandom::Sequence(SequenceRandom::new(puzzle.pieces.to_vec())),
+        None => randomizer.with_seed(seed.unwrap_or_else(random_seed)),
+    };
+    let handling = rules.handling.unwrap_or(Handling {
+        delayed_auto_shift: 0.18333333333,
+        auto_repeat_rate: 0.03333333333,
+    });
+    let keyboard = DasInputActions::new(
+        KeyboardQueryInputSource::new(),
+        handling.delayed_auto_shift,
+        handling.auto_repeat_rate,
+    );
+    let player = if bot {
+        Player::Bot {
+            bot: Box::new(BotInputActions::new(
+                SrsPieceSet,
+                HeuristicPlanner::new(AllTwistDetector),
+                handling,
+                BOT_PRESS_DELAY,
+            )),
+            keyboard,
+        }
+    } else {
+        Player::Human(keyboard)
+    };
     let mut game = Rustris::new(
+        rules,
         SrsPieceSet,
-        Queue::new(5, RandomBag::new(PieceType::all())),
-        DasInputActions::new(
-            KeyboardQueryInputSource::new(),
-            0.18333333333,
-            0.03333333333,
-        ),
+        random,
+        player,
         AllTwistDetector,
         TermionRenderer::new(),
     );
+    game.enable_undo();
 
-    // Initialize the simulation
+    match &puzzle {
+        Some(puzzle) => game.set_puzzle(puzzle),
+        None => game.set_mode(mode),
+    }
+    if let Some(snapshot) = &snapshot {
+        if let Err(error) = game.restore_snapshot(snapshot) {
+            eprintln!("Failed to resume: {}", error);
+            return;
+        }
+    }
+
+    // Initialize the simulation, which carries on from the snapshot when resuming
     game.init();
 
-    // Start a game loop that updates and renders the simulation
+    // Start a game loop that updates and renders the simulation until the game ends or is quit
     // - Uses the game_loop crate
-    game_loop(
+    let mut game_loop = game_loop(
         game,
         60,
         1.0,
-        |g| g.game.update(g.fixed_time_step()),
+        |g| {
+            g.game.update(g.fixed_time_step());
+            if g.game.is_quit() || g.game.outcome().is_some() {
+                g.exit();
+            }
+        },
         |g| g.game.render(g.fixed_time_step()),
     );
+
+    // Draw the final frame so the end of the game is visible
+    game_loop.game.render(0f64);
+
+    let stats = game_loop.game.stats();
+    if let Some(outcome) = game_loop.game.outcome() {
+        print!("{}\r\n", game_loop.game.mode().summary(outcome, &stats));
+    }
+    print!(
+        "Time: {}  Level: {}  Lines: {}  Pieces: {}  PPS: {:.2}  Score: {}  Faults: {}\r\n",
+        format_time(stats.time),
+        stats.level,
+        stats.lines_cleared,
+        stats.pieces_locked,
+        stats.pieces_per_second(),
+        stats.score,
+        stats.finesse_faults
+    );
+
+    if let Some(seed) = game_loop.game.seed() {
+        print!("Seed: {}\r\n", seed);
+    }
+
+    if let Some(path) = record_path {
+        let replay = game_loop.game.replay();
+        if let Err(error) = File::create(&path)
+            .map_err(ReplayError::from)
+            .and_then(|file| replay.save(file))
+        {
+            eprintln!("Failed to save replay to {}: {}", path, error);
+        }
+    }
+
+    if let (Some(path), None) = (save_path, game_loop.game.outcome()) {
+        match game_loop.game.snapshot().and_then(|snapshot| {
+            File::create(&path)
+                .map_err(SnapshotError::from)
+                .and_then(|file| snapshot.save(file))
+        }) {
+            Ok(()) => print!("Saved game to {}\r\n", path),
+            Err(error) => eprintln!("Failed to save game to {}: {}", path, error),
+        }
+    }
 }
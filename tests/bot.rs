@@ -0,0 +1,155 @@
+mod common;
+
+use common::FRAME;
+use rustris_bot::{BotInputActions, HeuristicPlanner, Move, Planner};
+use rustris_core::{
+    board::Board,
+    game::Rustris,
+    input::{GameView, Handling, InputActions},
+    piece::{Piece, PieceType, Rotation},
+    random::RandomBag,
+    renderer::NullRenderer,
+    rules::RuleSet,
+    twist::AllTwistDetector,
+};
+use rustris_srs::SrsPieceSet;
+
+type BotGame = Rustris<
+    SrsPieceSet,
+    RandomBag<PieceType>,
+    BotInputActions<SrsPieceSet, HeuristicPlanner<AllTwistDetector>>,
+    NullRenderer,
+    AllTwistDetector,
+>;
+
+fn bot_game(rules: RuleSet, seed: u64) -> BotGame {
+    // A small search keeps the tests quick in debug builds
+    let mut planner = HeuristicPlanner::new(AllTwistDetector);
+    planner.beam_width = 4;
+    planner.lookahead = 2;
+    let bot = BotInputActions::new(
+        SrsPieceSet,
+        planner,
+        Handling {
+            delayed_auto_shift: 6f64 * FRAME,
+            auto_repeat_rate: 0f64,
+        },
+        2f64 * FRAME,
+    );
+    let mut game = Rustris::new(
+        rules,
+        SrsPieceSet,
+        RandomBag::with_seed(PieceType::all(), seed),
+        bot,
+        AllTwistDetector,
+        NullRenderer,
+    );
+    game.init();
+    game
+}
+
+#[test]
+fn bots_clear_lines_without_topping_out() {
+    let mut game = bot_game(RuleSet::guideline(), 7);
+    while game.pieces_locked() < 60 {
+        game.update(FRAME);
+        assert_eq!(game.outcome(), None);
+    }
+
+    let stats = game.stats();
+    assert!(stats.lines_cleared >= 20, "{:?}", stats);
+    // The bot always takes the fewest presses
+    assert_eq!(stats.finesse_faults, 0);
+}
+
+#[test]
+fn bots_play_without_hard_drop() {
+    let mut game = bot_game(RuleSet::classic(), 7);
+    while game.pieces_locked() < 30 {
+        game.update(FRAME);
+        assert_eq!(game.outcome(), None);
+    }
+    assert!(game.stats().lines_cleared >= 5, "{:?}", game.stats());
+}
+
+#[test]
+fn bots_wait_while_the_game_is_paused() {
+    let mut bot = BotInputActions::new(
+        SrsPieceSet,
+        HeuristicPlanner::new(AllTwistDetector),
+        Handling {
+            delayed_auto_shift: 0f64,
+            auto_repeat_rate: 0f64,
+        },
+        0f64,
+    );
+    let rules = RuleSet::guideline();
+    let board = Board::new();
+    let view = |paused| GameView {
+        rules: &rules,
+        board: &board,
+        active_piece: Some(Piece {
+            piece_type: PieceType::T,
+            rotation: Rotation::Up,
+            position: rules.spawn_position,
+        }),
+        hold_piece_type: None,
+        can_hold: true,
+        next_piece_types: &[PieceType::O],
+        pieces_locked: 0,
+        consecutive_clears: 0,
+        back_to_back: false,
+        paused,
+    };
+
+    bot.observe(&view(true));
+    assert_eq!(bot.actions(FRAME), vec![]);
+    bot.observe(&view(false));
+    assert!(!bot.actions(FRAME).is_empty());
+}
+
+#[test]
+fn planners_hold_for_a_piece_from_the_queue_at_the_end_of_the_search() {
+    let mut planner = HeuristicPlanner::new(AllTwistDetector);
+    planner.lookahead = 1;
+    // A wide beam keeps boards that place the S first, which go on to place the I
+    planner.beam_width = 64;
+    let rules = RuleSet::guideline();
+    // Only the I piece from the queue clears the board, and an S placed first leaves a hole
+    let board = Board::from_diagram(
+        "
+        #########.
+        #########.
+        #########.
+        #########.
+        ",
+    );
+    let view = GameView {
+        rules: &rules,
+        board: &board,
+        active_piece: Some(Piece {
+            piece_type: PieceType::S,
+            rotation: Rotation::Up,
+            position: rules.spawn_position,
+        }),
+        hold_piece_type: None,
+        can_hold: true,
+        next_piece_types: &[PieceType::I],
+        pieces_locked: 0,
+        consecutive_clears: 0,
+        back_to_back: false,
+        paused: false,
+    };
+
+    let planned = Planner::plan(&mut planner, &view, &SrsPieceSet);
+    assert!(matches!(
+        planned,
+        Some(Move {
+            hold: true,
+            piece: Piece {
+                piece_type: PieceType::I,
+                ..
+            }
+        })
+    ));
+}
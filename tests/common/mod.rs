@@ -0,0 +1,107 @@
+#![allow(dead_code)]
+
+use rustris_core::{
+    game::{HeadlessRustris, Rustris},
+    input::{DasInputActions, ScriptedInputSource},
+    piece::PieceType,
+    puzzle::Puzzle,
+    random::{random_seed, RandomBag, SequenceRandom},
+    renderer::NullRenderer,
+    rules::RuleSet,
+    twist::AllTwistDetector,
+};
+use rustris_srs::SrsPieceSet;
+
+pub const FRAME: f64 = 1f64 / 60f64;
+
+pub type ScriptedGame = Rustris<
+    SrsPieceSet,
+    RandomBag<PieceType>,
+    DasInputActions<ScriptedInputSource>,
+    NullRenderer,
+    AllTwistDetector,
+>;
+
+/// Creates an initialized game that only deals the given piece types, driven by an input script
+pub fn scripted_game(piece_types: Vec<PieceType>, script: &str) -> ScriptedGame {
+    let mut game = seeded_scripted_game(piece_types, random_seed(), script);
+    game.init();
+    game
+}
+
+/// Creates a game like [scripted_game] with a fixed seed, without initializing it so it can be
+/// configured first
+pub fn seeded_scripted_game(piece_types: Vec<PieceType>, seed: u64, script: &str) -> ScriptedGame {
+    ruled_scripted_game(RuleSet::guideline(), piece_types, seed, script)
+}
+
+/// Creates a game like [seeded_scripted_game] that plays by the given rules
+pub fn ruled_scripted_game(
+    rules: RuleSet,
+    piece_types: Vec<PieceType>,
+    seed: u64,
+    script: &str,
+) -> ScriptedGame {
+    Rustris::new(
+        rules,
+        SrsPieceSet,
+        RandomBag::with_seed(piece_types, seed),
+        DasInputActions::new(
+            ScriptedInputSource::parse(script, FRAME).unwrap(),
+            10f64 * FRAME,
+            2f64 * FRAME,
+        ),
+        AllTwistDetector,
+        NullRenderer,
+    )
+}
+
+pub type HeadlessGame = HeadlessRustris<SrsPieceSet, RandomBag<PieceType>, AllTwistDetector>;
+
+/// Creates an initialized game that only deals the given piece types, driven by
+/// [Rustris::step]
+pub fn headless_game(piece_types: Vec<PieceType>, seed: u64) -> HeadlessGame {
+    let mut game = Rustris::headless(
+        RuleSet::guideline(),
+        SrsPieceSet,
+        RandomBag::with_seed(piece_types, seed),
+        AllTwistDetector,
+    );
+    game.init();
+    game
+}
+
+pub type PuzzleGame = Rustris<
+    SrsPieceSet,
+    SequenceRandom<PieceType>,
+    DasInputActions<ScriptedInputSource>,
+    NullRenderer,
+    AllTwistDetector,
+>;
+
+/// Creates an initialized game of the puzzle, driven by an input script
+pub fn puzzle_game(puzzle: &str, script: &str) -> PuzzleGame {
+    let puzzle = Puzzle::parse(puzzle).unwrap();
+    let mut game = Rustris::new(
+        RuleSet::guideline(),
+        SrsPieceSet,
+        puzzle.random(),
+        DasInputActions::new(
+            ScriptedInputSource::parse(script, FRAME).unwrap(),
+            10f64 * FRAME,
+            2f64 * FRAME,
+        ),
+        AllTwistDetector,
+        NullRenderer,
+    );
+    game.set_puzzle(&puzzle);
+    game.init();
+    game
+}
+
+/// Runs the game for the given number of frames
+pub fn run_frames<TGame: FnMut(f64)>(mut update: TGame, frames: usize) {
+    for _ in 0..frames {
+        update(FRAME);
+    }
+}
@@ -0,0 +1,311 @@
+mod common;
+
+use common::headless_game;
+use rustris_core::{
+    board::Board,
+    finesse::{finesse_path, FinesseActions, FinesseInput},
+    game::{GameEvent, Rustris},
+    input::{Action, Handling},
+    piece::{Piece, PieceType, Rotation},
+    placement::{placements, Placement},
+    position::Position,
+    random::RandomBag,
+    rules::{GravityCurve, RuleSet, TWENTY_G},
+    twist::AllTwistDetector,
+};
+use rustris_srs::SrsPieceSet;
+
+fn spawn(piece_type: PieceType) -> Piece {
+    Piece {
+        piece_type,
+        rotation: Rotation::Up,
+        position: Position::new(4, 19),
+    }
+}
+
+fn piece(piece_type: PieceType, rotation: Rotation, x: i32, y: i32) -> Piece {
+    Piece {
+        piece_type,
+        rotation,
+        position: Position::new(x, y),
+    }
+}
+
+#[test]
+fn finds_the_fewest_presses_on_an_empty_board() {
+    use FinesseInput::*;
+    let path = |target: Piece| {
+        finesse_path(
+            &Board::new(),
+            &SrsPieceSet,
+            &RuleSet::guideline(),
+            spawn(target.piece_type),
+            &target,
+        )
+        .unwrap()
+    };
+    assert_eq!(
+        path(piece(PieceType::O, Rotation::Up, 4, 0)),
+        vec![HardDrop]
+    );
+    assert_eq!(
+        path(piece(PieceType::O, Rotation::Up, 3, 0)),
+        vec![Left, HardDrop]
+    );
+    assert_eq!(
+        path(piece(PieceType::O, Rotation::Up, 0, 0)),
+        vec![DasLeft, HardDrop]
+    );
+    assert_eq!(
+        path(piece(PieceType::O, Rotation::Up, 1, 0)),
+        vec![DasLeft, Right, HardDrop]
+    );
+    assert_eq!(
+        path(piece(PieceType::T, Rotation::Left, 4, 1)),
+        vec![RotateLeft, HardDrop]
+    );
+    // Rotations that fill the same cells are the same placement
+    assert_eq!(
+        path(piece(PieceType::O, Rotation::Down, 8, 0)),
+        vec![DasRight, HardDrop]
+    );
+}
+
+#[test]
+fn paths_reach_every_placement_on_an_empty_board() {
+    let board = Board::new();
+    for piece_type in PieceType::all() {
+        for placement in placements(&board, &SrsPieceSet, &AllTwistDetector, spawn(piece_type)) {
+            let path = finesse_path(
+                &board,
+                &SrsPieceSet,
+                &RuleSet::guideline(),
+                spawn(piece_type),
+                &placement.piece,
+            )
+            .unwrap();
+
+            let mut piece = spawn(piece_type);
+            for input in &path {
+                piece = input.apply(&board, &SrsPieceSet, &piece).unwrap_or(piece);
+            }
+            let landed = Placement { piece, ..placement };
+            assert_eq!(
+                landed.cells(&SrsPieceSet),
+                placement.cells(&SrsPieceSet),
+                "{:?}",
+                path
+            );
+        }
+    }
+}
+
+#[test]
+fn tucks_soft_drop_under_overhangs() {
+    let board = Board::from_diagram(
+        "
+            ######....
+            ..........
+            ..........
+            ",
+    );
+    assert_eq!(
+        finesse_path(
+            &board,
+            &SrsPieceSet,
+            &RuleSet::guideline(),
+            spawn(PieceType::O),
+            &piece(PieceType::O, Rotation::Up, 0, 0)
+        ),
+        Some(vec![
+            FinesseInput::DasRight,
+            FinesseInput::SoftDrop,
+            FinesseInput::DasLeft,
+            FinesseInput::HardDrop
+        ])
+    );
+}
+
+#[test]
+fn paths_are_played_with_the_handling() {
+    let board = Board::new();
+    let mut active = spawn(PieceType::O);
+    let path = finesse_path(
+        &board,
+        &SrsPieceSet,
+        &RuleSet::guideline(),
+        active,
+        &piece(PieceType::O, Rotation::Up, 0, 0),
+    )
+    .unwrap();
+    let handling = Handling {
+        delayed_auto_shift: 0.5,
+        auto_repeat_rate: 0.125,
+    };
+    let mut actions = FinesseActions::new(path, handling);
+
+    assert_eq!(actions.press(), Some(Action::MoveLeft));
+    active.position.x -= 1;
+    // DAS charges over two frames, then shifts every half frame until the piece reaches the wall
+    let mut frames = vec![];
+    while actions.is_holding() {
+        let shifts = actions.hold(&board, &SrsPieceSet, active, 0.25);
+        active.position.x -= shifts.len() as i32;
+        frames.push(shifts);
+    }
+    assert_eq!(
+        frames,
+        vec![
+            vec![],
+            vec![Action::AutoShiftLeft],
+            vec![Action::AutoShiftLeft, Action::AutoShiftLeft]
+        ]
+    );
+    assert_eq!(actions.press(), Some(Action::HardDrop));
+    assert!(actions.is_finished());
+}
+
+#[test]
+fn paths_soft_drop_when_hard_drop_is_disabled() {
+    let rules = RuleSet {
+        hard_drop_enabled: false,
+        ..RuleSet::guideline()
+    };
+    let path = |target: Piece| {
+        finesse_path(
+            &Board::new(),
+            &SrsPieceSet,
+            &rules,
+            spawn(target.piece_type),
+            &target,
+        )
+    };
+    assert_eq!(
+        path(piece(PieceType::O, Rotation::Up, 3, 0)),
+        Some(vec![FinesseInput::Left, FinesseInput::SoftDrop])
+    );
+
+    // A piece tucked onto its target is left to lock
+    let board = Board::from_diagram(
+        "
+            ######....
+            ..........
+            ..........
+            ",
+    );
+    assert_eq!(
+        finesse_path(
+            &board,
+            &SrsPieceSet,
+            &rules,
+            spawn(PieceType::O),
+            &piece(PieceType::O, Rotation::Up, 0, 0)
+        ),
+        Some(vec![
+            FinesseInput::DasRight,
+            FinesseInput::SoftDrop,
+            FinesseInput::DasLeft
+        ])
+    );
+}
+
+#[test]
+fn unreachable_targets_have_no_path() {
+    let board = Board::from_diagram("##########");
+    assert_eq!(
+        finesse_path(
+            &board,
+            &SrsPieceSet,
+            &RuleSet::guideline(),
+            spawn(PieceType::O),
+            &piece(PieceType::O, Rotation::Up, 0, 0)
+        ),
+        None
+    );
+}
+
+#[test]
+fn games_count_presses_beyond_the_fewest_each_piece_needed() {
+    let mut game = headless_game(vec![PieceType::O], 1);
+
+    // Tapping to the wall takes 3 more presses than holding
+    let result = game.step(&[
+        Action::MoveLeft,
+        Action::MoveLeft,
+        Action::MoveLeft,
+        Action::MoveLeft,
+        Action::HardDrop,
+    ]);
+    assert!(matches!(
+        result.events[..],
+        [GameEvent::FinesseFault(3), GameEvent::PieceLocked(_)]
+    ));
+
+    // Auto shifting from a held key isn't a press
+    let result = game.step(&[
+        Action::MoveLeft,
+        Action::AutoShiftLeft,
+        Action::AutoShiftLeft,
+        Action::AutoShiftLeft,
+        Action::HardDrop,
+    ]);
+    assert!(matches!(result.events[..], [GameEvent::PieceLocked(_)]));
+
+    // Presses are counted from spawn, so turning back on a held piece is still a fault
+    game.step(&[Action::RotateRight]);
+    game.step(&[Action::Hold]);
+    let result = game.step(&[Action::RotateLeft, Action::RotateRight, Action::HardDrop]);
+    assert!(matches!(
+        result.events[..],
+        [GameEvent::FinesseFault(2), GameEvent::PieceLocked(_)]
+    ));
+    assert_eq!(game.stats().finesse_faults, 5);
+}
+
+#[test]
+fn faults_are_not_counted_at_20g() {
+    let mut game = Rustris::headless(
+        RuleSet {
+            gravity: GravityCurve::Fixed(TWENTY_G),
+            ..RuleSet::guideline()
+        },
+        SrsPieceSet,
+        RandomBag::with_seed(vec![PieceType::O], 1),
+        AllTwistDetector,
+    );
+    game.init();
+
+    let result = game.step(&[
+        Action::MoveLeft,
+        Action::MoveLeft,
+        Action::MoveLeft,
+        Action::MoveLeft,
+        Action::HardDrop,
+    ]);
+    assert!(matches!(result.events[..], [GameEvent::PieceLocked(_)]));
+    assert_eq!(game.stats().finesse_faults, 0);
+}
+
+#[test]
+fn finesse_training_sends_pieces_placed_with_faults_back_to_spawn() {
+    let mut game = Rustris::headless(
+        RuleSet {
+            finesse_training: true,
+            ..RuleSet::guideline()
+        },
+        SrsPieceSet,
+        RandomBag::with_seed(vec![PieceType::T], 1),
+        AllTwistDetector,
+    );
+    game.init();
+
+    let result = game.step(&[Action::MoveLeft, Action::MoveRight, Action::HardDrop]);
+    assert_eq!(result.events, vec![GameEvent::FinesseFault(2)]);
+    assert_eq!(game.active_piece(), Some(spawn(PieceType::T)));
+    assert_eq!(*game.board(), Board::from_diagram(""));
+    assert_eq!(game.pieces_locked(), 0);
+
+    let result = game.step(&[Action::HardDrop]);
+    assert!(matches!(result.events[..], [GameEvent::PieceLocked(_)]));
+    assert_eq!(game.stats().finesse_faults, 2);
+}
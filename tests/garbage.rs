@@ -0,0 +1,114 @@
+mod common;
+
+use common::{ruled_scripted_game, run_frames, scripted_game};
+use rustris_core::{
+    board::Board,
+    garbage::GarbageQueue,
+    mode::Outcome,
+    piece::PieceType,
+    rules::{EntryDelay, RuleSet},
+};
+
+#[test]
+fn garbage_is_inserted_when_a_piece_locks_without_clearing() {
+    let mut game = scripted_game(vec![PieceType::O], "0 hard_drop");
+    game.set_garbage_queue(GarbageQueue::with_seed(0f64, 7));
+    game.receive_garbage(2);
+    run_frames(|dt| game.update(dt), 1);
+
+    let rows = &game.board().rows;
+    let hole = rows[0].iter().position(|cell| !cell).unwrap();
+    assert_eq!(rows[0].iter().filter(|cell| **cell).count(), 9);
+    assert_eq!(rows[1], rows[0]);
+    assert!(!rows[1][hole]);
+
+    // The piece locked before the garbage pushed it up
+    assert_eq!(rows[2].iter().filter(|cell| **cell).count(), 2);
+    assert_eq!(rows[3].iter().filter(|cell| **cell).count(), 2);
+    assert_eq!(game.pending_garbage(), 0);
+}
+
+#[test]
+fn garbage_that_pushes_blocks_off_the_board_tops_out() {
+    // The entry delay keeps the next piece from topping out by spawning into the garbage
+    let rules = RuleSet {
+        are: EntryDelay::Fixed(1f64),
+        ..RuleSet::guideline()
+    };
+    let mut game = ruled_scripted_game(rules, vec![PieceType::O], 1, "0 hard_drop");
+    game.init();
+    // The O piece's top row reaches the top of the board after 38 rows
+    game.receive_garbage(39);
+    run_frames(|dt| game.update(dt), 1);
+    assert_eq!(game.outcome(), Some(Outcome::ToppedOut));
+
+    let mut board = Board::new();
+    board.rows[39][0] = true;
+    assert!(board.insert_garbage(0));
+    assert!(!board.insert_garbage(0));
+}
+
+#[test]
+fn holes_past_the_wall_are_kept_on_the_board() {
+    let mut board = Board::new();
+    assert!(!board.insert_garbage(12));
+    assert_eq!(
+        board.rows[0],
+        [true, true, true, true, true, true, true, true, true, false]
+    );
+}
+
+#[test]
+fn garbage_waits_while_lines_are_being_cleared() {
+    let mut game = scripted_game(
+        vec![PieceType::I],
+        "
+        0 left_press
+        20 left_release hard_drop
+        21 right_press
+        40 right_release hard_drop
+        41 rotate_right
+        42 hard_drop
+        43 rotate_right left
+        44 hard_drop
+        ",
+    );
+    run_frames(|dt| game.update(dt), 44);
+    game.receive_garbage(3);
+    run_frames(|dt| game.update(dt), 1);
+
+    // The last I clears a single, which sends nothing and leaves the garbage waiting
+    assert_eq!(game.board().rows[0].iter().filter(|cell| **cell).count(), 2);
+    assert_eq!(game.pending_garbage(), 3);
+    assert_eq!(game.take_outgoing_garbage(), 0);
+}
+
+#[test]
+fn attack_cancels_incoming_garbage() {
+    // Five O pieces side by side clear a double, which is also an all clear
+    let mut game = scripted_game(
+        vec![PieceType::O],
+        "
+        0 left_press
+        20 left_release hard_drop
+        21 left
+        23 left
+        25 hard_drop
+        26 hard_drop
+        27 right
+        29 right
+        31 hard_drop
+        32 right_press
+        52 right_release hard_drop
+        ",
+    );
+    run_frames(|dt| game.update(dt), 52);
+    game.receive_garbage(4);
+    run_frames(|dt| game.update(dt), 1);
+
+    assert!(game.board().is_all_clear());
+    assert_eq!(game.attack_sent(), 11);
+    assert_eq!(game.pending_garbage(), 0);
+    assert_eq!(game.take_outgoing_garbage(), 7);
+    assert_eq!(game.take_outgoing_garbage(), 0);
+}
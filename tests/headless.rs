@@ -0,0 +1,67 @@
+mod common;
+
+use common::headless_game;
+use rustris_core::{board::Board, game::GameEvent, input::Action, mode::Outcome, piece::PieceType};
+
+#[test]
+fn stepping_reports_locks_and_clears() {
+    let mut game = headless_game(vec![PieceType::O], 1);
+
+    // Fill every column with an O, from the left wall to the right wall, holding the move key
+    // to reach the walls
+    let moves = [
+        vec![
+            Action::MoveLeft,
+            Action::AutoShiftLeft,
+            Action::AutoShiftLeft,
+            Action::AutoShiftLeft,
+        ],
+        vec![Action::MoveLeft; 2],
+        vec![],
+        vec![Action::MoveRight; 2],
+    ];
+    for actions in moves {
+        let mut actions = actions;
+        actions.push(Action::HardDrop);
+        let result = game.step(&actions);
+        assert!(matches!(result.events[..], [GameEvent::PieceLocked(_)]));
+    }
+
+    let result = game.step(&[
+        Action::MoveRight,
+        Action::AutoShiftRight,
+        Action::AutoShiftRight,
+        Action::AutoShiftRight,
+        Action::HardDrop,
+    ]);
+    match result.events[..] {
+        [GameEvent::PieceLocked(piece), GameEvent::LinesCleared(clear)] => {
+            assert_eq!(piece.position.x, 8);
+            assert_eq!(clear.lines, 2);
+            assert!(clear.all_clear);
+        }
+        _ => panic!("unexpected events {:?}", result.events),
+    }
+    assert_eq!(*game.board(), Board::from_diagram(""));
+
+    // Nothing happens on a frame without actions
+    assert_eq!(game.step(&[]).events, vec![]);
+}
+
+#[test]
+fn stepping_reports_when_the_game_ends() {
+    let mut game = headless_game(vec![PieceType::O], 1);
+
+    let mut frames = 0;
+    let outcome = loop {
+        let result = game.step(&[Action::HardDrop]);
+        if let Some(outcome) = result.game_over() {
+            break outcome;
+        }
+        frames += 1;
+        assert!(frames < 20);
+    };
+    assert_eq!(outcome, Outcome::ToppedOut);
+    assert_eq!(game.outcome(), Some(Outcome::ToppedOut));
+    assert_eq!(game.step(&[Action::HardDrop]).events, vec![]);
+}
@@ -0,0 +1,285 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{ruled_scripted_game, run_frames, scripted_game, seeded_scripted_game, FRAME};
+use rustris_core::{
+    game::ModeContext,
+    garbage::{GarbageQueue, MODE_GARBAGE_STREAM},
+    mode::{GameMode, Mode, Outcome, Stats, Verdict},
+    piece::PieceType,
+    renderer::HudEntry,
+    rules::{LockDelay, RuleSet},
+};
+
+/// Five O pieces placed side by side, clearing two lines on the last hard drop at frame 52
+const TWO_LINES: &str = "
+    0 left_press
+    20 left_release hard_drop
+    21 left
+    23 left
+    25 hard_drop
+    26 hard_drop
+    27 right
+    29 right
+    31 hard_drop
+    32 right_press
+    52 right_release hard_drop
+    ";
+
+/// Repeats the two line clear from [TWO_LINES] the given number of times
+fn clear_lines_in_pairs(pairs: u64) -> String {
+    let mut script = String::new();
+    for pair in 0..pairs {
+        for line in TWO_LINES.lines().filter(|line| !line.trim().is_empty()) {
+            let (frame, inputs) = line.trim().split_once(' ').unwrap();
+            let frame = frame.parse::<u64>().unwrap() + pair * 53;
+            script.push_str(&format!("{} {}\n", frame, inputs));
+        }
+    }
+    script
+}
+
+#[test]
+fn sprint_ends_when_the_line_goal_is_reached() {
+    let mut game = scripted_game(vec![PieceType::O], TWO_LINES);
+    game.set_mode(Mode::Sprint { lines: 2 });
+    run_frames(|dt| game.update(dt), 60);
+
+    assert_eq!(game.outcome(), Some(Outcome::Completed));
+    assert_eq!(game.active_piece(), None);
+
+    // The timer stops on the frame the goal was reached
+    let stats = game.stats();
+    assert_eq!(stats.lines_cleared, 2);
+    assert_eq!(stats.pieces_locked, 5);
+    let expected = Duration::from_secs_f64(53f64 * FRAME);
+    assert!(stats.time.abs_diff(expected) < Duration::from_micros(10));
+}
+
+#[test]
+fn endless_games_continue_past_any_line_count() {
+    let mut game = scripted_game(vec![PieceType::O], TWO_LINES);
+    run_frames(|dt| game.update(dt), 60);
+
+    assert_eq!(game.outcome(), None);
+    assert_eq!(game.stats().lines_cleared, 2);
+}
+
+#[test]
+fn stacking_to_the_top_ends_the_game() {
+    let script = (0..20)
+        .map(|frame| format!("{} hard_drop", frame))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let mut game = scripted_game(vec![PieceType::O], &script);
+    run_frames(|dt| game.update(dt), 20);
+
+    assert_eq!(game.outcome(), Some(Outcome::ToppedOut));
+    assert_eq!(game.pieces_locked(), 10);
+}
+
+#[test]
+fn ultra_ends_when_time_runs_out() {
+    let mut game = scripted_game(vec![PieceType::O], TWO_LINES);
+    game.set_mode(Mode::Ultra {
+        duration: Duration::from_secs(1),
+    });
+    run_frames(|dt| game.update(dt), 59);
+    assert_eq!(game.outcome(), None);
+    run_frames(|dt| game.update(dt), 2);
+
+    assert_eq!(game.outcome(), Some(Outcome::Completed));
+    assert_eq!(game.stats().time, Duration::from_secs(1));
+
+    // Five hard drops of 19 lines, then a double that is also an all clear
+    assert_eq!(game.stats().score, 5 * 19 * 2 + 300 + 1200);
+}
+
+#[test]
+fn marathon_levels_up_every_ten_lines() {
+    let mut game = scripted_game(vec![PieceType::O], &clear_lines_in_pairs(6));
+    game.set_mode(Mode::Marathon { lines: Some(12) });
+    run_frames(|dt| game.update(dt), 5 * 53);
+    assert_eq!(game.stats().level, 2);
+    assert_eq!(game.outcome(), None);
+
+    run_frames(|dt| game.update(dt), 53);
+    assert_eq!(game.stats().lines_cleared, 12);
+    assert_eq!(game.outcome(), Some(Outcome::Completed));
+}
+
+#[test]
+fn endless_marathon_has_no_line_goal() {
+    let mut game = scripted_game(vec![PieceType::O], &clear_lines_in_pairs(6));
+    game.set_mode(Mode::Marathon { lines: None });
+    run_frames(|dt| game.update(dt), 6 * 53);
+    assert_eq!(game.stats().lines_cleared, 12);
+    assert_eq!(game.outcome(), None);
+}
+
+/// Rotates a vertical I piece into the given column and hard drops it, starting at the frame
+fn drop_vertical_i(frame: u64, column: usize) -> String {
+    // The I piece is in column 5 after rotating at spawn
+    let (direction, taps) = if column < 5 {
+        ("left", 5 - column)
+    } else {
+        ("right", column - 5)
+    };
+    let mut script = format!("{} rotate_right\n", frame);
+    for tap in 0..taps as u64 {
+        script.push_str(&format!("{} {}\n", frame + 1 + 2 * tap, direction));
+    }
+    script.push_str(&format!("{} hard_drop\n", frame + 20));
+    script
+}
+
+#[test]
+fn cheese_refills_garbage_until_every_line_is_cleared() {
+    // Cheese holes come from the game's seed, so find one where the holes aren't stacked
+    let seed = (0..)
+        .find(|seed| {
+            let holes = GarbageQueue::with_stream(1f64, *seed, MODE_GARBAGE_STREAM).holes(2);
+            holes[0] != holes[1]
+        })
+        .unwrap();
+    let holes = GarbageQueue::with_stream(1f64, seed, MODE_GARBAGE_STREAM).holes(2);
+
+    let script = drop_vertical_i(0, holes[0]) + &drop_vertical_i(21, holes[1]);
+    let mut game = seeded_scripted_game(vec![PieceType::I], seed, &script);
+    game.set_mode(Mode::Cheese { lines: 2, rows: 1 });
+    game.init();
+
+    let rows = &game.board().rows;
+    assert_eq!(rows[0].iter().filter(|cell| **cell).count(), 9);
+    assert!(!rows[0][holes[0]]);
+    assert!(rows[1].iter().all(|cell| !cell));
+
+    // Clearing the first row brings in the second one below the rest of the I piece
+    run_frames(|dt| game.update(dt), 21);
+    assert_eq!(game.stats().garbage_cleared, 1);
+    assert!(!game.board().rows[0][holes[1]]);
+    assert!(game.board().rows[1][holes[0]]);
+    assert_eq!(game.outcome(), None);
+
+    run_frames(|dt| game.update(dt), 21);
+    assert_eq!(game.stats().garbage_cleared, 2);
+    assert_eq!(game.outcome(), Some(Outcome::Completed));
+}
+
+#[test]
+fn survival_raises_garbage_until_the_player_tops_out() {
+    // Without lock delay the piece stays active however long it rests on the stack
+    let rules = RuleSet {
+        lock_delay: LockDelay::Infinite,
+        ..RuleSet::guideline()
+    };
+    let mut game = ruled_scripted_game(rules, vec![PieceType::T], 1, "");
+    game.set_mode(Mode::Survival {
+        interval: Duration::from_secs(1),
+    });
+    game.init();
+
+    run_frames(|dt| game.update(dt), 61);
+    let rows = &game.board().rows;
+    assert_eq!(rows[0].iter().filter(|cell| **cell).count(), 9);
+    let hole = GarbageQueue::with_stream(1f64, 1, MODE_GARBAGE_STREAM).holes(1)[0];
+    assert!(!rows[0][hole]);
+    assert!(rows[1].iter().all(|cell| !cell));
+
+    // The piece is never dropped, so it rests on the garbage and is pushed up with it
+    let mut frames = 61;
+    while game.outcome().is_none() && frames < 60 * 60 {
+        game.update(FRAME);
+        frames += 1;
+    }
+    assert_eq!(game.outcome(), Some(Outcome::ToppedOut));
+    assert_eq!(game.stats().lines_cleared, 0);
+    assert!(game.stats().time > Duration::from_secs(20));
+}
+
+#[test]
+fn arcade_levels_go_up_for_every_piece() {
+    let script = (0..3)
+        .map(|piece| format!("{} hard_drop", piece * 30))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let mut game = ruled_scripted_game(RuleSet::arcade(), vec![PieceType::O], 1, &script);
+    game.set_mode(Mode::Arcade);
+    game.init();
+    assert_eq!(game.stats().level, 0);
+
+    run_frames(|dt| game.update(dt), 61);
+    assert_eq!(game.pieces_locked(), 3);
+    assert_eq!(game.stats().level, 3);
+    assert_eq!(
+        game.mode().hud(&game.stats())[..3],
+        [
+            HudEntry::new("LEVEL", 3),
+            HudEntry::new("NEXT", 100),
+            HudEntry::new("GRADE", 9)
+        ]
+    );
+}
+
+/// Starts on a row of garbage with its hole on the right, and gives the player a fixed number
+/// of pieces to clear it
+#[derive(Clone)]
+struct DigMode {
+    pieces: u32,
+}
+
+impl GameMode for DigMode {
+    fn name(&self) -> &str {
+        "dig"
+    }
+
+    fn start(&mut self, context: &mut ModeContext) {
+        context.insert_garbage(&[9]);
+    }
+
+    fn verdict(&self, stats: &Stats) -> Verdict {
+        if stats.garbage_cleared > 0 {
+            Verdict::Win
+        } else if stats.pieces_locked >= self.pieces {
+            Verdict::Lose
+        } else {
+            Verdict::Continue
+        }
+    }
+
+    fn hud(&self, stats: &Stats) -> Vec<HudEntry> {
+        vec![HudEntry::new("LEFT", self.pieces - stats.pieces_locked)]
+    }
+}
+
+#[test]
+fn custom_modes_fail_from_their_verdict() {
+    let mut game = seeded_scripted_game(vec![PieceType::O], 1, "0 hard_drop\n1 hard_drop");
+    game.set_game_mode(DigMode { pieces: 2 });
+    game.init();
+    assert!(!game.board().rows[0][9]);
+    assert!(game.board().rows[0][..9].iter().all(|cell| *cell));
+
+    run_frames(|dt| game.update(dt), 1);
+    assert_eq!(game.outcome(), None);
+    assert_eq!(
+        game.mode().hud(&game.stats()),
+        vec![HudEntry::new("LEFT", 1)]
+    );
+
+    run_frames(|dt| game.update(dt), 1);
+    assert_eq!(game.outcome(), Some(Outcome::Failed));
+}
+
+#[test]
+fn custom_modes_complete_from_their_verdict() {
+    let mut game = seeded_scripted_game(vec![PieceType::I], 1, &drop_vertical_i(0, 9));
+    game.set_game_mode(DigMode { pieces: 2 });
+    game.init();
+
+    run_frames(|dt| game.update(dt), 21);
+    assert_eq!(game.stats().garbage_cleared, 1);
+    assert_eq!(game.outcome(), Some(Outcome::Completed));
+    assert_eq!(game.mode().name(), "dig");
+}
@@ -0,0 +1,95 @@
+mod common;
+
+use rustris_core::{
+    board::Board,
+    piece::{Piece, PieceType, Rotation},
+    placement::placements,
+    position::Position,
+    twist::{AllTwistDetector, ThreeCornerTTwistDetector, TwistKind},
+};
+use rustris_srs::SrsPieceSet;
+
+fn spawn(piece_type: PieceType) -> Piece {
+    Piece {
+        piece_type,
+        rotation: Rotation::Up,
+        position: Position::new(4, 19),
+    }
+}
+
+fn board(diagram: &str) -> Board {
+    Board::from_diagram(diagram)
+}
+
+#[test]
+fn every_column_and_rotation_is_found_on_an_empty_board() {
+    let count = |piece_type| {
+        placements(
+            &Board::new(),
+            &SrsPieceSet,
+            &AllTwistDetector,
+            spawn(piece_type),
+        )
+        .len()
+    };
+    // Rotations that fill the same cells are only counted once
+    assert_eq!(count(PieceType::O), 9);
+    assert_eq!(count(PieceType::I), 7 + 10);
+    assert_eq!(count(PieceType::S), 8 + 9);
+    assert_eq!(count(PieceType::T), 8 + 8 + 9 + 9);
+}
+
+#[test]
+fn pieces_tuck_under_overhangs() {
+    let board = board(
+        "
+        ######....
+        ..........
+        ..........
+        ",
+    );
+    let placements = placements(&board, &SrsPieceSet, &AllTwistDetector, spawn(PieceType::O));
+    assert!(placements
+        .iter()
+        .any(|placement| placement.piece.position == Position::new(0, 0)));
+    // Nothing can rest on the overhang from below it
+    assert!(placements
+        .iter()
+        .all(|placement| placement.piece.position.y != 1));
+}
+
+#[test]
+fn pieces_spin_into_slots_as_twists() {
+    let board = board(
+        "
+        ###.......
+        ##...#####
+        ###.######
+        ",
+    );
+    let placements = placements(
+        &board,
+        &SrsPieceSet,
+        &ThreeCornerTTwistDetector,
+        spawn(PieceType::T),
+    );
+    let twist = placements
+        .iter()
+        .find(|placement| {
+            placement.piece.rotation == Rotation::Down
+                && placement.piece.position == Position::new(3, 1)
+        })
+        .expect("the T-spin double slot should be reachable");
+    assert_eq!(twist.twist, TwistKind::Full);
+    assert!(placements
+        .iter()
+        .filter(|placement| placement.twist != TwistKind::None)
+        .all(|placement| placement.piece.position.y < 3));
+}
+
+#[test]
+fn obstructed_pieces_have_nowhere_to_go() {
+    let mut board = Board::new();
+    board.rows[19] = [true; 10];
+    assert!(placements(&board, &SrsPieceSet, &AllTwistDetector, spawn(PieceType::T)).is_empty());
+}
@@ -0,0 +1,124 @@
+mod common;
+
+use common::{puzzle_game, run_frames};
+use rustris_core::{mode::Outcome, piece::PieceType};
+
+/// Turns the T upside down and drops it into the gap, clearing both rows
+const DROP_T_INTO_GAP: &str = "
+    0 rotate_right
+    1 rotate_right
+    2 left
+    4 hard_drop
+    ";
+
+#[test]
+fn reaching_the_goal_solves_the_puzzle() {
+    let mut game = puzzle_game(
+        "
+        pieces: T O
+        goal: lines 2
+        board:
+        ##...#####
+        ###.######
+        ",
+        DROP_T_INTO_GAP,
+    );
+    run_frames(|dt| game.update(dt), 5);
+
+    assert_eq!(game.outcome(), Some(Outcome::Completed));
+    assert!(game.board().is_all_clear());
+}
+
+#[test]
+fn puzzles_start_with_the_hold_piece() {
+    let mut game = puzzle_game(
+        "
+        pieces: O
+        hold: T
+        goal: all-clear
+        board:
+        ##...#####
+        ###.######
+        ",
+        "
+        0 hold
+        1 rotate_right
+        2 rotate_right
+        3 left
+        5 hard_drop
+        ",
+    );
+    run_frames(|dt| game.update(dt), 6);
+
+    assert_eq!(game.outcome(), Some(Outcome::Completed));
+    assert_eq!(game.hold_piece_type(), Some(PieceType::O));
+}
+
+#[test]
+fn running_out_of_pieces_fails_the_puzzle() {
+    let mut game = puzzle_game(
+        "
+        pieces: T
+        goal: attack 4
+        board:
+        #.........
+        ##...#####
+        ###.######
+        ",
+        DROP_T_INTO_GAP,
+    );
+    run_frames(|dt| game.update(dt), 5);
+
+    // The double only sends 1 line
+    assert_eq!(game.attack_sent(), 1);
+    assert_eq!(game.outcome(), Some(Outcome::Failed));
+}
+
+#[test]
+fn holding_needs_a_piece_to_take_its_place() {
+    let mut game = puzzle_game(
+        "
+        pieces: T
+        goal: all-clear
+        board:
+        ##...#####
+        ###.######
+        ",
+        "0 hold",
+    );
+    run_frames(|dt| game.update(dt), 1);
+
+    assert_eq!(game.hold_piece_type(), None);
+    assert_eq!(game.active_piece().unwrap().piece_type, PieceType::T);
+    assert_eq!(game.outcome(), None);
+}
+
+#[test]
+fn the_held_piece_is_played_once_the_queue_runs_out() {
+    let mut game = puzzle_game(
+        "
+        pieces: I
+        hold: O
+        goal: lines 2
+        board:
+        ##..######
+        ##..######
+        ",
+        "
+        0 right
+        1 right
+        2 right
+        3 hard_drop
+        10 left
+        11 left
+        12 hard_drop
+        ",
+    );
+    run_frames(|dt| game.update(dt), 4);
+    assert_eq!(game.outcome(), None);
+    assert_eq!(game.active_piece().unwrap().piece_type, PieceType::O);
+    assert_eq!(game.hold_piece_type(), None);
+
+    run_frames(|dt| game.update(dt), 9);
+    assert_eq!(game.outcome(), Some(Outcome::Completed));
+}
@@ -0,0 +1,290 @@
+mod common;
+
+use common::{puzzle_game, run_frames, FRAME};
+use rustris_core::{
+    game::Rustris,
+    garbage::GarbageQueue,
+    input::{DasInputActions, Handling, ScriptedInputSource},
+    mode::{Mode, Outcome},
+    piece::PieceType,
+    puzzle::PuzzleGoal,
+    random::{RandomBag, SequenceRandom},
+    renderer::NullRenderer,
+    replay::{Replay, ReplayInputActions},
+    rules::RuleSet,
+    twist::AllTwistDetector,
+};
+use rustris_srs::SrsPieceSet;
+
+const SCRIPT: &str = "
+    0 left_press
+    20 left_release hard_drop
+    25 rotate_right right
+    30 hard_drop
+    31 hold
+    40 right_press soft_drop_press
+    60 right_release rotate_left
+    70 soft_drop_release
+    80 hard_drop
+    90 hold rotate_left left
+    100 hard_drop
+";
+
+#[test]
+fn replay_reproduces_the_game() {
+    let mut game = Rustris::new(
+        RuleSet::guideline(),
+        SrsPieceSet,
+        RandomBag::with_seed(PieceType::all(), 2024),
+        DasInputActions::new(
+            ScriptedInputSource::parse(SCRIPT, FRAME).unwrap(),
+            10f64 * FRAME,
+            2f64 * FRAME,
+        ),
+        AllTwistDetector,
+        NullRenderer,
+    );
+    game.init();
+    run_frames(|dt| game.update(dt), 120);
+
+    let mut file = Vec::new();
+    game.replay().save(&mut file).unwrap();
+    let replay = Replay::load(file.as_slice()).unwrap();
+    assert_eq!(replay.frames, 120);
+    assert_eq!(replay.seed, Some(2024));
+    assert_eq!(
+        replay.handling,
+        Some(Handling {
+            delayed_auto_shift: 10f64 * FRAME,
+            auto_repeat_rate: 2f64 * FRAME,
+        })
+    );
+
+    let mut playback = Rustris::new(
+        replay.rules.clone(),
+        SrsPieceSet,
+        replay.random(),
+        ReplayInputActions::new(&replay),
+        AllTwistDetector,
+        NullRenderer,
+    );
+    playback.set_replay(&replay);
+    playback.init();
+    run_frames(
+        |_| playback.update(replay.frame_time),
+        replay.frames as usize,
+    );
+
+    assert_eq!(playback.board().rows, game.board().rows);
+    assert_eq!(playback.active_piece(), game.active_piece());
+    assert_eq!(playback.hold_piece_type(), game.hold_piece_type());
+    // The playback is dealt the recorded pieces rather than using the seed
+    assert_eq!(playback.seed(), None);
+    assert_eq!(
+        Replay {
+            seed: replay.seed,
+            ..playback.replay()
+        },
+        replay
+    );
+}
+
+#[test]
+fn replay_reproduces_received_garbage() {
+    let mut game = Rustris::new(
+        RuleSet::guideline(),
+        SrsPieceSet,
+        RandomBag::with_seed(PieceType::all(), 7),
+        DasInputActions::new(
+            ScriptedInputSource::parse(SCRIPT, FRAME).unwrap(),
+            10f64 * FRAME,
+            2f64 * FRAME,
+        ),
+        AllTwistDetector,
+        NullRenderer,
+    );
+    game.set_garbage_queue(GarbageQueue::with_seed(1f64, 99));
+    game.init();
+    run_frames(|dt| game.update(dt), 10);
+    game.receive_garbage(3);
+    run_frames(|dt| game.update(dt), 110);
+    assert_eq!(game.pending_garbage(), 0);
+
+    let mut file = Vec::new();
+    game.replay().save(&mut file).unwrap();
+    let replay = Replay::load(file.as_slice()).unwrap();
+    assert_eq!(replay.garbage_seed, 99);
+
+    let mut playback = Rustris::new(
+        replay.rules.clone(),
+        SrsPieceSet,
+        replay.random(),
+        ReplayInputActions::new(&replay),
+        AllTwistDetector,
+        NullRenderer,
+    );
+    playback.set_replay(&replay);
+    playback.init();
+    run_frames(
+        |_| playback.update(replay.frame_time),
+        replay.frames as usize,
+    );
+
+    assert_eq!(playback.board().rows, game.board().rows);
+    assert_eq!(playback.replay().events, replay.events);
+}
+
+#[test]
+fn replay_recreates_the_puzzle() {
+    let mut game = puzzle_game(
+        "
+        pieces: T O
+        goal: lines 2
+        board:
+        ##...#####
+        ###.######
+        ",
+        "
+        0 rotate_right
+        1 rotate_right
+        2 left
+        4 hard_drop
+        ",
+    );
+    run_frames(|dt| game.update(dt), 5);
+    assert_eq!(game.outcome(), Some(Outcome::Completed));
+
+    let replay = game.replay();
+    assert_eq!(
+        replay.mode,
+        Some(Mode::Puzzle {
+            goal: PuzzleGoal::Lines(2)
+        })
+    );
+
+    let mut playback = Rustris::new(
+        replay.rules.clone(),
+        SrsPieceSet,
+        replay.random(),
+        ReplayInputActions::new(&replay),
+        AllTwistDetector,
+        NullRenderer,
+    );
+    playback.set_replay(&replay);
+    playback.init();
+    run_frames(
+        |_| playback.update(replay.frame_time),
+        replay.frames as usize,
+    );
+
+    assert_eq!(playback.outcome(), Some(Outcome::Completed));
+    assert!(playback.board().is_all_clear());
+    assert_eq!(playback.replay(), replay);
+}
+
+#[test]
+fn replay_recreates_the_garbage_of_unseeded_games() {
+    let mut game = Rustris::new(
+        RuleSet::guideline(),
+        SrsPieceSet,
+        SequenceRandom::new(PieceType::all()),
+        DasInputActions::new(
+            ScriptedInputSource::parse(SCRIPT, FRAME).unwrap(),
+            10f64 * FRAME,
+            2f64 * FRAME,
+        ),
+        AllTwistDetector,
+        NullRenderer,
+    );
+    game.set_mode(Mode::Cheese { lines: 20, rows: 8 });
+    game.init();
+    run_frames(|dt| game.update(dt), 120);
+
+    let replay = game.replay();
+    assert_eq!(replay.seed, None);
+    let mut playback = Rustris::new(
+        replay.rules.clone(),
+        SrsPieceSet,
+        replay.random(),
+        ReplayInputActions::new(&replay),
+        AllTwistDetector,
+        NullRenderer,
+    );
+    playback.set_replay(&replay);
+    playback.init();
+    run_frames(
+        |_| playback.update(replay.frame_time),
+        replay.frames as usize,
+    );
+
+    assert_eq!(playback.board().rows, game.board().rows);
+}
+
+#[test]
+fn playback_stops_when_the_recorded_pieces_run_out() {
+    let mut game = Rustris::new(
+        RuleSet::guideline(),
+        SrsPieceSet,
+        RandomBag::with_seed(PieceType::all(), 2024),
+        DasInputActions::new(
+            ScriptedInputSource::parse(SCRIPT, FRAME).unwrap(),
+            10f64 * FRAME,
+            2f64 * FRAME,
+        ),
+        AllTwistDetector,
+        NullRenderer,
+    );
+    game.init();
+    run_frames(|dt| game.update(dt), 120);
+    let mut replay = game.replay();
+    replay.pieces.truncate(2);
+
+    let mut playback = Rustris::new(
+        replay.rules.clone(),
+        SrsPieceSet,
+        replay.random(),
+        ReplayInputActions::new(&replay),
+        AllTwistDetector,
+        NullRenderer,
+    );
+    playback.set_replay(&replay);
+    playback.init();
+    run_frames(
+        |_| playback.update(replay.frame_time),
+        replay.frames as usize,
+    );
+
+    assert_eq!(playback.pieces_locked(), 2);
+    assert_eq!(playback.outcome(), Some(Outcome::Failed));
+}
+
+#[test]
+fn restoring_a_keyframe_rewinds_the_game() {
+    let mut game = Rustris::new(
+        RuleSet::guideline(),
+        SrsPieceSet,
+        SequenceRandom::new(PieceType::all()),
+        DasInputActions::new(
+            ScriptedInputSource::parse(SCRIPT, FRAME).unwrap(),
+            10f64 * FRAME,
+            2f64 * FRAME,
+        ),
+        AllTwistDetector,
+        NullRenderer,
+    );
+    game.init();
+    run_frames(|dt| game.update(dt), 25);
+    let keyframe = game.keyframe();
+    let pieces_locked = game.pieces_locked();
+
+    run_frames(|dt| game.update(dt), 95);
+    let board = game.board().rows.to_vec();
+    let replay = game.replay();
+    assert!(game.pieces_locked() > pieces_locked);
+
+    game.restore_keyframe(&keyframe);
+    assert_eq!(game.pieces_locked(), pieces_locked);
+    run_frames(|dt| game.update(dt), 95);
+    assert_eq!(game.board().rows, board);
+    assert_eq!(game.replay(), replay);
+}
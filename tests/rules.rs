@@ -0,0 +1,151 @@
+mod common;
+
+use common::{ruled_scripted_game, run_frames, FRAME};
+use rustris_core::{
+    mode::Outcome,
+    piece::{PieceType, Rotation},
+    position::Position,
+    rules::{GravityCurve, LockDelay, LockOut, RuleSet, TWENTY_G},
+};
+
+#[test]
+fn pieces_lock_after_resting_for_the_lock_delay() {
+    let mut game = ruled_scripted_game(
+        RuleSet::guideline(),
+        vec![PieceType::O],
+        1,
+        "0 soft_drop_press",
+    );
+    game.init();
+
+    // Soft dropping reaches the floor in under half a second
+    run_frames(|dt| game.update(dt), 30);
+    assert_eq!(game.active_piece().unwrap().position.y, 0);
+    assert_eq!(game.pieces_locked(), 0);
+
+    run_frames(|dt| game.update(dt), 30);
+    assert_eq!(game.pieces_locked(), 1);
+}
+
+#[test]
+fn moving_resets_the_lock_delay_a_limited_number_of_times() {
+    // Tap left and right every 20 frames once the piece is on the floor
+    let mut script = "0 soft_drop_press\n".to_string();
+    for tap in 0..20 {
+        let direction = if tap % 2 == 0 { "left" } else { "right" };
+        script.push_str(&format!("{} {}\n", 30 + 20 * tap, direction));
+    }
+    let rules = RuleSet {
+        lock_delay: LockDelay::Timed {
+            seconds: 0.5,
+            resets: 3,
+        },
+        ..RuleSet::guideline()
+    };
+    let mut game = ruled_scripted_game(rules, vec![PieceType::O], 1, &script);
+    game.init();
+
+    // Each reset keeps the piece active, but the timer carries on after the last one
+    run_frames(|dt| game.update(dt), 100);
+    assert_eq!(game.pieces_locked(), 0);
+    run_frames(|dt| game.update(dt), 30);
+    assert_eq!(game.pieces_locked(), 1);
+}
+
+#[test]
+fn classic_rules_lock_on_landing_and_wait_before_spawning() {
+    let mut game = ruled_scripted_game(
+        RuleSet::classic(),
+        vec![PieceType::O],
+        1,
+        "0 hard_drop hold soft_drop_press",
+    );
+    game.init();
+
+    // Hard drop and hold are disabled, so only soft drop moves the piece
+    run_frames(|dt| game.update(dt), 1);
+    assert!(game.active_piece().unwrap().position.y > 0);
+    assert_eq!(game.hold_piece_type(), None);
+
+    let mut frames = 1;
+    while game.pieces_locked() == 0 {
+        game.update(FRAME);
+        frames += 1;
+    }
+    assert!(frames < 60);
+    assert_eq!(game.active_piece(), None);
+
+    // The next piece spawns after the entry delay
+    run_frames(|dt| game.update(dt), 9);
+    assert_eq!(game.active_piece(), None);
+    run_frames(|dt| game.update(dt), 1);
+    assert!(game.active_piece().is_some());
+}
+
+/// The arcade rules with 20G from the start, instead of once the level gets high enough
+fn twenty_g_rules() -> RuleSet {
+    RuleSet {
+        gravity: GravityCurve::Fixed(TWENTY_G),
+        ..RuleSet::arcade()
+    }
+}
+
+#[test]
+fn twenty_g_drops_pieces_onto_the_stack_as_they_spawn() {
+    let mut game = ruled_scripted_game(twenty_g_rules(), vec![PieceType::I], 1, "");
+    game.init();
+    assert_eq!(game.active_piece().unwrap().position.y, 0);
+
+    // The arcade lock delay is 30 frames
+    run_frames(|dt| game.update(dt), 30);
+    assert_eq!(game.pieces_locked(), 0);
+    run_frames(|dt| game.update(dt), 1);
+    assert_eq!(game.pieces_locked(), 1);
+}
+
+#[test]
+fn rotating_during_the_entry_delay_rotates_the_next_piece() {
+    let script = "
+        0 hard_drop
+        10 rotate_right
+        ";
+    let mut game = ruled_scripted_game(twenty_g_rules(), vec![PieceType::I], 1, script);
+    game.init();
+
+    // The arcade entry delay is 25 frames
+    run_frames(|dt| game.update(dt), 25);
+    assert_eq!(game.active_piece(), None);
+    run_frames(|dt| game.update(dt), 1);
+    let piece = game.active_piece().unwrap();
+    assert_eq!(piece.rotation, Rotation::Right);
+    // Standing upright on the first I, after falling straight down at 20G
+    assert_eq!(piece.position.y, 3);
+}
+
+#[test]
+fn locking_above_the_playfield_tops_out() {
+    let script = (0..11)
+        .map(|frame| format!("{} hard_drop", frame))
+        .collect::<Vec<_>>()
+        .join("\n");
+    // Spawning above the playfield leaves room for the 11th O piece to lock there
+    let game = |lock_out| {
+        let rules = RuleSet {
+            spawn_position: Position::new(4, 22),
+            lock_out,
+            ..RuleSet::guideline()
+        };
+        let mut game = ruled_scripted_game(rules, vec![PieceType::O], 1, &script);
+        game.init();
+        run_frames(|dt| game.update(dt), 11);
+        game
+    };
+
+    let game_with_lock_out = game(LockOut::Entirely);
+    assert_eq!(game_with_lock_out.outcome(), Some(Outcome::ToppedOut));
+    assert_eq!(game_with_lock_out.pieces_locked(), 11);
+
+    let game_without_lock_out = game(LockOut::Never);
+    assert_eq!(game_without_lock_out.outcome(), None);
+    assert_eq!(game_without_lock_out.pieces_locked(), 11);
+}
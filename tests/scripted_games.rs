@@ -0,0 +1,93 @@
+mod common;
+
+use common::{run_frames, scripted_game};
+use rustris_core::{
+    board::Board,
+    piece::{PieceType, Rotation},
+};
+
+#[test]
+fn tap_left_twice_rotate_and_hard_drop() {
+    let mut game = scripted_game(
+        vec![PieceType::T],
+        "
+        0 left
+        2 left
+        4 rotate_right
+        6 hard_drop
+        ",
+    );
+    run_frames(|dt| game.update(dt), 10);
+
+    assert_eq!(
+        *game.board(),
+        Board::from_diagram(
+            "
+            ..#.......
+            ..##......
+            ..#.......
+            "
+        )
+    );
+}
+
+#[test]
+fn held_movement_shifts_to_the_wall() {
+    let mut game = scripted_game(
+        vec![PieceType::O],
+        "
+        0 right_press
+        30 hard_drop
+        ",
+    );
+    run_frames(|dt| game.update(dt), 31);
+
+    assert_eq!(
+        *game.board(),
+        Board::from_diagram(
+            "
+            ........##
+            ........##
+            "
+        )
+    );
+}
+
+#[test]
+fn hold_swaps_the_active_piece() {
+    let mut game = scripted_game(vec![PieceType::I], "0 rotate_right\n1 hold");
+    run_frames(|dt| game.update(dt), 2);
+
+    // The piece taken from the queue spawns in its initial rotation
+    assert_eq!(game.hold_piece_type(), Some(PieceType::I));
+    assert_eq!(game.active_piece().unwrap().rotation, Rotation::Up);
+}
+
+#[test]
+fn filling_rows_clears_them() {
+    let mut game = scripted_game(
+        vec![PieceType::I],
+        "
+        0 left_press
+        20 left_release hard_drop
+        21 right_press
+        40 right_release hard_drop
+        41 rotate_right
+        42 hard_drop
+        43 rotate_right left
+        44 hard_drop
+        ",
+    );
+    run_frames(|dt| game.update(dt), 45);
+
+    assert_eq!(
+        *game.board(),
+        Board::from_diagram(
+            "
+            ....##....
+            ....##....
+            ....##....
+            "
+        )
+    );
+}
@@ -0,0 +1,95 @@
+mod common;
+
+use common::HeadlessGame;
+use rustris_core::{
+    game::Rustris,
+    input::Action,
+    mode::Mode,
+    piece::PieceType,
+    random::RandomBag,
+    rules::RuleSet,
+    snapshot::{GameSnapshot, SnapshotError},
+    twist::AllTwistDetector,
+};
+use rustris_srs::SrsPieceSet;
+
+fn cheese_game() -> HeadlessGame {
+    let mut game = Rustris::headless(
+        RuleSet::guideline(),
+        SrsPieceSet,
+        RandomBag::with_seed(PieceType::all(), 5),
+        AllTwistDetector,
+    );
+    game.set_mode(Mode::Cheese {
+        lines: 100,
+        rows: 4,
+    });
+    game
+}
+
+/// Moves each piece to a different column before dropping it, so the stack grows evenly
+fn actions(frame: usize) -> Vec<Action> {
+    match (frame % 12, frame / 12 % 5) {
+        (3, 0) => vec![Action::MoveLeft; 4],
+        (3, 1) => vec![Action::MoveLeft; 2],
+        (3, 3) => vec![Action::MoveRight; 2],
+        (3, 4) => vec![Action::MoveRight; 4],
+        (11, _) => vec![Action::HardDrop],
+        _ => vec![],
+    }
+}
+
+#[test]
+fn resumed_games_carry_on_exactly_where_they_left_off() {
+    let mut game = cheese_game();
+    game.init();
+    for frame in 0..100 {
+        game.step(&actions(frame));
+    }
+
+    let mut saved = vec![];
+    game.snapshot().unwrap().save(&mut saved).unwrap();
+    let snapshot = GameSnapshot::load(saved.as_slice()).unwrap();
+    let mut resumed = cheese_game();
+    resumed.restore_snapshot(&snapshot).unwrap();
+    resumed.init();
+
+    for frame in 100..200 {
+        let events = game.step(&actions(frame));
+        assert_eq!(resumed.step(&actions(frame)), events);
+    }
+    assert_eq!(game.outcome(), None);
+    assert!(game.stats().pieces_locked > 12);
+    assert_eq!(resumed.stats(), game.stats());
+    assert_eq!(resumed.board().rows, game.board().rows);
+    assert_eq!(resumed.active_piece(), game.active_piece());
+    assert_eq!(resumed.replay(), game.replay());
+}
+
+#[test]
+fn snapshots_only_restore_into_the_same_mode() {
+    let mut game = cheese_game();
+    game.init();
+    let snapshot = game.snapshot().unwrap();
+
+    let mut sprint = cheese_game();
+    sprint.set_mode(Mode::Sprint { lines: 40 });
+    assert!(matches!(
+        sprint.restore_snapshot(&snapshot),
+        Err(SnapshotError::ModeMismatch { .. })
+    ));
+}
+
+#[test]
+fn load_rejects_unknown_versions() {
+    let mut saved = vec![];
+    cheese_game().snapshot().unwrap().save(&mut saved).unwrap();
+    let saved = String::from_utf8(saved).unwrap();
+
+    // Snapshots from before finesse faults were counted are missing fields
+    for version in [1, 99] {
+        let saved = saved.replacen("\"version\":2,", &format!("\"version\":{},", version), 1);
+        let loaded = GameSnapshot::<RandomBag<PieceType>>::load(saved.as_bytes());
+        assert!(matches!(loaded, Err(SnapshotError::UnsupportedVersion(v)) if v == version));
+    }
+}
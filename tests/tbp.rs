@@ -0,0 +1,134 @@
+mod common;
+
+use std::{env, fs, process::Command};
+
+use common::FRAME;
+use rustris_bot::BotInputActions;
+use rustris_core::{
+    game::Rustris,
+    input::Handling,
+    piece::{Piece, PieceSet, PieceType, Rotation},
+    position::Position,
+    random::RandomBag,
+    renderer::NullRenderer,
+    rules::RuleSet,
+    twist::AllTwistDetector,
+};
+use rustris_srs::SrsPieceSet;
+use rustris_tbp::{Location, Orientation, TbpBot, TbpPlanner};
+
+fn cells(piece: &Piece) -> Vec<Position> {
+    let mut cells: Vec<Position> = SrsPieceSet
+        .units(&piece.piece_type, &piece.rotation)
+        .map(|unit| unit + piece.position)
+        .to_vec();
+    cells.sort_by_key(|cell| (cell.y, cell.x));
+    cells
+}
+
+#[test]
+fn locations_fill_the_same_cells_as_pieces() {
+    for piece_type in PieceType::all() {
+        for rotation in [
+            Rotation::Up,
+            Rotation::Right,
+            Rotation::Down,
+            Rotation::Left,
+        ] {
+            let piece = Piece {
+                piece_type,
+                rotation,
+                position: Position::new(4, 19),
+            };
+            let location = Location::from_piece(&SrsPieceSet, &piece);
+            assert_eq!(cells(&location.to_piece(&SrsPieceSet)), cells(&piece));
+        }
+    }
+
+    // The protocol turns O pieces around their bottom left cell, which SRS keeps in place
+    let location = Location::from_piece(
+        &SrsPieceSet,
+        &Piece {
+            piece_type: PieceType::O,
+            rotation: Rotation::Right,
+            position: Position::new(4, 0),
+        },
+    );
+    assert_eq!(
+        (location.orientation, location.x, location.y),
+        (Orientation::East, 4, 1)
+    );
+}
+
+/// A bot that fills the board with O pieces from the left, suggesting every column each time so
+/// the filled ones have to be skipped, and logs the messages it's sent
+const FAKE_BOT: &str = r#"
+echo '{"type":"info","name":"Fake","version":"1.0","author":"rustris","features":[]}'
+while read -r line; do
+    echo "$line" >> "$TBP_LOG"
+    case "$line" in
+        *'"type":"rules"'*) echo '{"type":"ready"}' ;;
+        *'"type":"suggest"'*)
+            moves=""
+            for x in 0 2 4 6 8; do
+                moves="$moves{\"location\":{\"type\":\"O\",\"orientation\":\"north\",\"x\":$x,\"y\":0},\"spin\":\"none\"},"
+            done
+            echo "{\"type\":\"suggestion\",\"moves\":[${moves%,}]}"
+            ;;
+        *'"type":"quit"'*) exit 0 ;;
+    esac
+done
+"#;
+
+#[cfg(unix)]
+#[test]
+fn external_bots_play_through_the_protocol() {
+    let log = env::temp_dir().join(format!("rustris-tbp-{}.log", std::process::id()));
+    let _ = fs::remove_file(&log);
+
+    let bot = TbpBot::spawn(
+        Command::new("sh")
+            .args(["-c", FAKE_BOT])
+            .env("TBP_LOG", &log),
+    )
+    .unwrap();
+    assert_eq!(bot.info().name, "Fake");
+
+    let mut game = Rustris::new(
+        RuleSet::guideline(),
+        SrsPieceSet,
+        RandomBag::with_seed(vec![PieceType::O], 1),
+        BotInputActions::new(
+            SrsPieceSet,
+            TbpPlanner::new(bot),
+            Handling {
+                delayed_auto_shift: 0f64,
+                auto_repeat_rate: 0f64,
+            },
+            0f64,
+        ),
+        AllTwistDetector,
+        NullRenderer,
+    );
+    game.init();
+    while game.pieces_locked() < 10 {
+        game.update(FRAME);
+        assert_eq!(game.outcome(), None);
+        if let Some(error) = game.input_actions().planner().error() {
+            panic!("{}", error);
+        }
+    }
+
+    // Every five pieces fill two rows, which clear and leave the board as it started
+    assert_eq!(game.stats().lines_cleared, 4);
+    assert!(game.board().is_all_clear());
+
+    drop(game);
+    let log = fs::read_to_string(&log).unwrap();
+    for message in ["rules", "start", "suggest", "play", "new_piece", "quit"] {
+        let message = format!("\"type\":\"{}\"", message);
+        assert!(log.contains(&message), "{} not sent in:\n{}", message, log);
+    }
+    // The bot is kept in sync, so it only needs to be started once
+    assert_eq!(log.matches("\"type\":\"start\"").count(), 1);
+}
@@ -0,0 +1,183 @@
+mod common;
+
+use common::{run_frames, HeadlessGame};
+use rustris_core::{
+    game::Rustris,
+    input::Action,
+    piece::PieceType,
+    random::{Random, RandomBag},
+    renderer::{HistoryPosition, NullRenderer},
+    replay::ReplayInputActions,
+    rules::RuleSet,
+    twist::AllTwistDetector,
+};
+use rustris_srs::SrsPieceSet;
+
+fn practice_game(undo_limit: usize) -> HeadlessGame {
+    let mut game = Rustris::headless(
+        RuleSet {
+            undo_limit,
+            ..RuleSet::guideline()
+        },
+        SrsPieceSet,
+        RandomBag::with_seed(PieceType::all(), 3),
+        AllTwistDetector,
+    );
+    game.enable_undo();
+    game.init();
+    game
+}
+
+/// Drops the active piece after moving it the given number of columns left
+fn drop_left(game: &mut HeadlessGame, columns: usize) {
+    let mut actions = vec![Action::MoveLeft; columns];
+    actions.push(Action::HardDrop);
+    game.step(&actions);
+}
+
+#[test]
+fn undo_takes_back_placements_and_redo_plays_them_again() {
+    let mut game = practice_game(10);
+    drop_left(&mut game, 3);
+    let board = game.board().rows.to_vec();
+    let active_piece = game.active_piece();
+    drop_left(&mut game, 0);
+    let redone_board = game.board().rows.to_vec();
+    assert_eq!(
+        game.history_position(),
+        Some(HistoryPosition {
+            placement: 2,
+            placements: 2
+        })
+    );
+
+    game.step(&[Action::Undo]);
+    assert_eq!(game.board().rows, board);
+    assert_eq!(game.active_piece(), active_piece);
+    assert_eq!(game.pieces_locked(), 1);
+    assert_eq!(
+        game.history_position(),
+        Some(HistoryPosition {
+            placement: 1,
+            placements: 2
+        })
+    );
+
+    game.step(&[Action::Redo]);
+    assert_eq!(game.board().rows, redone_board);
+    assert_eq!(game.pieces_locked(), 2);
+
+    // A new placement replaces the ones that could be redone
+    game.step(&[Action::Undo]);
+    drop_left(&mut game, 1);
+    game.step(&[Action::Redo]);
+    assert_ne!(game.board().rows, redone_board);
+    assert_eq!(
+        game.history_position(),
+        Some(HistoryPosition {
+            placement: 2,
+            placements: 2
+        })
+    );
+}
+
+#[test]
+fn undo_only_goes_back_as_far_as_the_limit() {
+    let mut game = practice_game(2);
+    for columns in 0..4 {
+        drop_left(&mut game, columns);
+    }
+    for _ in 0..4 {
+        game.step(&[Action::Undo]);
+    }
+    assert_eq!(game.pieces_locked(), 2);
+}
+
+#[test]
+fn undo_is_disabled_by_the_presets() {
+    let mut game = practice_game(RuleSet::guideline().undo_limit);
+    drop_left(&mut game, 0);
+    game.step(&[Action::Undo]);
+    assert_eq!(game.pieces_locked(), 1);
+    assert_eq!(game.history_position(), None);
+}
+
+#[test]
+fn keyframes_start_the_history_again() {
+    let mut game = practice_game(10);
+    drop_left(&mut game, 3);
+    let keyframe = game.keyframe();
+    drop_left(&mut game, 0);
+    drop_left(&mut game, 1);
+
+    game.restore_keyframe(&keyframe);
+    assert_eq!(
+        game.history_position(),
+        Some(HistoryPosition {
+            placement: 0,
+            placements: 0
+        })
+    );
+    game.step(&[Action::Undo]);
+    assert_eq!(game.pieces_locked(), 1);
+}
+
+#[test]
+fn replays_play_back_undos() {
+    let mut game = practice_game(10);
+    drop_left(&mut game, 3);
+    drop_left(&mut game, 0);
+    game.step(&[Action::Undo]);
+    game.step(&[Action::Undo]);
+    drop_left(&mut game, 1);
+    drop_left(&mut game, 4);
+    game.step(&[]);
+
+    let replay = game.replay();
+    // The pieces dealt again after the undos are only recorded once, followed by the queue
+    assert_eq!(replay.pieces.len(), 3 + 5);
+    let mut playback = Rustris::new(
+        replay.rules.clone(),
+        SrsPieceSet,
+        replay.random(),
+        ReplayInputActions::new(&replay),
+        AllTwistDetector,
+        NullRenderer,
+    );
+    playback.set_replay(&replay);
+    playback.enable_undo();
+    playback.init();
+    run_frames(
+        |_| playback.update(replay.frame_time),
+        replay.frames as usize,
+    );
+    assert_eq!(playback.board().rows, game.board().rows);
+    assert_eq!(playback.active_piece(), game.active_piece());
+}
+
+/// A randomizer that can't be cloned, so games using it can't keep an undo history
+struct OnlyO;
+
+impl Random<PieceType> for OnlyO {
+    fn next(&mut self) -> PieceType {
+        PieceType::O
+    }
+}
+
+#[test]
+fn games_without_undo_do_not_need_to_clone_their_randomizer() {
+    let mut game = Rustris::headless(
+        RuleSet {
+            undo_limit: 10,
+            ..RuleSet::guideline()
+        },
+        SrsPieceSet,
+        OnlyO,
+        AllTwistDetector,
+    );
+    game.init();
+    game.step(&[Action::HardDrop]);
+    game.step(&[Action::Undo]);
+    assert_eq!(game.pieces_locked(), 1);
+    assert_eq!(game.history_position(), None);
+}